@@ -103,12 +103,14 @@ impl Hitable for AxisAlignedBoundingBox {
             //t_max = fp::min(t_max, fp::max(t_1, t_2));
         }
         //warn!("Time elapsed for AABB intersection: {:?}", start.elapsed());
-        if t_max > fp::max(t_min, 0.0) {
+        //Inclusive so that flat boxes around axis-aligned planar primitives still get hit
+        if t_max >= fp::max(t_min, 0.0) {
             let intersection_info = IntersectionInfo {
                 t_intersection: 0.0,
                 point_of_intersection: Point3::from(0.0),
                 normal: Vec3::from(0.0),
                 is_aabb: true,
+                ..Default::default()
             };
             return Some(intersection_info);
         }
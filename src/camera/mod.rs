@@ -6,5 +6,6 @@ pub trait Camera {
     fn new(origin_: Point3, look_at: Point3, up_: Vec3) -> Self
     where
         Self: Sized;
-    fn generate_camera_ray(&self, x: i32, y: i32, film: &Film) -> Ray;
+    //pixel_sample is the position inside the pixel in [0,1)^2, (0.5, 0.5) being the pixel center
+    fn generate_camera_ray(&self, x: i32, y: i32, pixel_sample: Point2, film: &Film) -> Ray;
 }
@@ -59,10 +59,10 @@ impl Camera for PinholeCamera {
         phc
     }
 
-    fn generate_camera_ray(&self, x: i32, y: i32, film: &Film) -> Ray {
+    fn generate_camera_ray(&self, x: i32, y: i32, pixel_sample: Point2, film: &Film) -> Ray {
         //Find point inside pixel coordinates
-        let u: fp = (fp::from(x) + 0.5) / fp::from(film.width);
-        let v: fp = (fp::from(y) + 0.5) / fp::from(film.height);
+        let u: fp = (fp::from(x) + pixel_sample.x) / fp::from(film.width);
+        let v: fp = (fp::from(y) + pixel_sample.y) / fp::from(film.height);
 
         //Find height and width of the image plane based on FOV, distance and aspect ratio
         //Use Y-FOV
@@ -0,0 +1,343 @@
+use crate::integrators::baseintegrator::Integrators;
+use crate::preview::tevpreview::TEV_DEFAULT_ADDRESS;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: sayo_pbr_rs [OPTIONS] <SCENE_FILE>
+
+Renders a Tungsten style .toml scene file.
+
+Options:
+  -o, --output <FILE>          Output file, a .pfm or .exr file overrides renderer.hdr_output_file
+                               and a .png file renderer.output_file
+  -d, --output-dir <DIR>       Directory for relative output files [default: next to the scene file]
+  -s, --spp <N>                Samples per pixel
+  -t, --threads <N>            Number of render threads [default: one per core]
+  -i, --integrator <NAME>      direct_lighting, path_tracer_bsdf or path_tracer_nee,
+                               overrides integrator.type
+  -r, --resolution <WxH>       Film resolution such as 1280x720, overrides camera.resolution
+      --seed <N>               Seed for the per tile random number generators [default: 0]
+      --preview <SINK>         Where to show the image while rendering, none or tev [default: none]
+      --tev-address <ADDRESS>  Address of a running tev instance [default: 127.0.0.1:14158]
+  -l, --log-level <LEVEL>      error, warn, info, debug or trace [default: info, or RUST_LOG]
+  -h, --help                   Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewKind {
+    None,
+    Tev,
+}
+
+//Everything given on the command line. Options left out fall back to the scene file or defaults.
+pub struct CommandLine {
+    pub scene_file: PathBuf,
+    //-o fills the slot matching the file's extension
+    pub hdr_output_file: Option<PathBuf>,
+    pub ldr_output_file: Option<PathBuf>,
+    pub output_directory: Option<PathBuf>,
+    pub samples_count: Option<u32>,
+    pub threads: Option<usize>,
+    pub integrator: Option<Integrators>,
+    pub resolution: Option<(i32, i32)>,
+    pub seed: u64,
+    pub preview: PreviewKind,
+    pub tev_address: String,
+    pub log_level: Option<String>,
+}
+
+pub enum CliError {
+    //--help was given, not an error as such but parsing stops there
+    HelpRequested,
+    Invalid(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::HelpRequested => write!(f, "help requested"),
+            CliError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl CommandLine {
+    //Parse the arguments following the program name
+    pub fn parse(args: &[String]) -> Result<CommandLine, CliError> {
+        let mut scene_file: Option<PathBuf> = None;
+        let mut command_line = CommandLine {
+            scene_file: PathBuf::new(),
+            hdr_output_file: None,
+            ldr_output_file: None,
+            output_directory: None,
+            samples_count: None,
+            threads: None,
+            integrator: None,
+            resolution: None,
+            seed: 0,
+            preview: PreviewKind::None,
+            tev_address: TEV_DEFAULT_ADDRESS.to_string(),
+            log_level: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            //Both "--spp 16" and "--spp=16"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || -> Result<String, CliError> {
+                match inline_value.clone() {
+                    Some(value) => Ok(value),
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| CliError::Invalid(format!("{} expects a value", flag))),
+                }
+            };
+            match flag {
+                "-h" | "--help" => return Err(CliError::HelpRequested),
+                "-o" | "--output" => {
+                    let file = PathBuf::from(value()?);
+                    let extension = file
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .map(|extension| extension.to_ascii_lowercase());
+                    match extension.as_deref() {
+                        Some("pfm" | "exr") => command_line.hdr_output_file = Some(file),
+                        Some("png") => command_line.ldr_output_file = Some(file),
+                        _ => {
+                            return Err(CliError::Invalid(format!(
+                                "{} expects a .pfm, .exr or .png file, got {}",
+                                flag,
+                                file.display()
+                            )))
+                        }
+                    }
+                }
+                "-d" | "--output-dir" => {
+                    command_line.output_directory = Some(PathBuf::from(value()?))
+                }
+                "-s" | "--spp" => {
+                    command_line.samples_count = Some(parse_positive(flag, &value()?)?)
+                }
+                "-t" | "--threads" => command_line.threads = Some(parse_positive(flag, &value()?)?),
+                "-i" | "--integrator" => {
+                    let name = value()?;
+                    command_line.integrator =
+                        Some(Integrators::from_name(&name).ok_or_else(|| {
+                            CliError::Invalid(format!("unknown integrator {}", name))
+                        })?)
+                }
+                "-r" | "--resolution" => {
+                    let resolution = value()?;
+                    let (width, height) = resolution.split_once(['x', 'X']).ok_or_else(|| {
+                        CliError::Invalid(format!(
+                            "{} expects WIDTHxHEIGHT, got {}",
+                            flag, resolution
+                        ))
+                    })?;
+                    command_line.resolution =
+                        Some((parse_positive(flag, width)?, parse_positive(flag, height)?));
+                }
+                "--seed" => command_line.seed = parse_number(flag, &value()?)?,
+                "--preview" => {
+                    command_line.preview = match value()?.as_str() {
+                        "none" => PreviewKind::None,
+                        "tev" => PreviewKind::Tev,
+                        other => {
+                            return Err(CliError::Invalid(format!("unknown preview {}", other)))
+                        }
+                    }
+                }
+                "--tev-address" => command_line.tev_address = value()?,
+                "-l" | "--log-level" => {
+                    let level = value()?.to_ascii_lowercase();
+                    match level.as_str() {
+                        "off" | "error" | "warn" | "info" | "debug" | "trace" => {
+                            command_line.log_level = Some(level)
+                        }
+                        _ => return Err(CliError::Invalid(format!("unknown log level {}", level))),
+                    }
+                }
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(CliError::Invalid(format!("unknown option {}", flag)))
+                }
+                _ => match scene_file {
+                    None => scene_file = Some(PathBuf::from(arg)),
+                    Some(_) => {
+                        return Err(CliError::Invalid(format!(
+                            "only one scene file can be rendered, got another one: {}",
+                            arg
+                        )))
+                    }
+                },
+            }
+        }
+
+        command_line.scene_file =
+            scene_file.ok_or_else(|| CliError::Invalid("no scene file given".to_string()))?;
+        Ok(command_line)
+    }
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| CliError::Invalid(format!("{} expects a number, got {}", flag, value)))
+}
+
+fn parse_positive<T: FromStr + PartialOrd + Default>(
+    flag: &str,
+    value: &str,
+) -> Result<T, CliError> {
+    let number: T = parse_number(flag, value)?;
+    if number <= T::default() {
+        return Err(CliError::Invalid(format!(
+            "{} expects a positive number, got {}",
+            flag, value
+        )));
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CommandLine, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        CommandLine::parse(&args)
+    }
+
+    fn parse_ok(args: &[&str]) -> CommandLine {
+        match parse(args) {
+            Ok(command_line) => command_line,
+            Err(error) => panic!("{:?} failed: {}", args, error),
+        }
+    }
+
+    fn parse_error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{:?} should fail", args),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn defaults_without_options() {
+        let command_line = parse_ok(&["scene.toml"]);
+        assert_eq!(command_line.scene_file, PathBuf::from("scene.toml"));
+        assert!(command_line.hdr_output_file.is_none());
+        assert!(command_line.ldr_output_file.is_none());
+        assert!(command_line.samples_count.is_none());
+        assert!(command_line.threads.is_none());
+        assert!(command_line.log_level.is_none());
+        assert_eq!(command_line.seed, 0);
+        assert_eq!(command_line.preview, PreviewKind::None);
+    }
+
+    #[test]
+    fn output_file_slot_follows_the_extension() {
+        let command_line = parse_ok(&["-o", "out.exr", "scene.toml"]);
+        assert_eq!(command_line.hdr_output_file, Some(PathBuf::from("out.exr")));
+        assert!(command_line.ldr_output_file.is_none());
+        let command_line = parse_ok(&["scene.toml", "--output=images/out.PFM"]);
+        assert_eq!(
+            command_line.hdr_output_file,
+            Some(PathBuf::from("images/out.PFM"))
+        );
+        let command_line = parse_ok(&["--output", "out.png", "scene.toml"]);
+        assert_eq!(command_line.ldr_output_file, Some(PathBuf::from("out.png")));
+        assert!(command_line.hdr_output_file.is_none());
+        assert_eq!(
+            parse_error(&["-o", "out.jpg", "scene.toml"]),
+            "-o expects a .pfm, .exr or .png file, got out.jpg"
+        );
+        assert_eq!(parse_error(&["scene.toml", "-o"]), "-o expects a value");
+    }
+
+    #[test]
+    fn threads_and_samples_are_positive_numbers() {
+        let command_line = parse_ok(&["-t", "4", "--spp", "16", "scene.toml"]);
+        assert_eq!(command_line.threads, Some(4));
+        assert_eq!(command_line.samples_count, Some(16));
+        let command_line = parse_ok(&["--threads=2", "-s", "1", "scene.toml"]);
+        assert_eq!(command_line.threads, Some(2));
+        assert_eq!(command_line.samples_count, Some(1));
+        assert_eq!(
+            parse_error(&["-t", "0", "scene.toml"]),
+            "-t expects a positive number, got 0"
+        );
+        assert_eq!(
+            parse_error(&["--spp", "many", "scene.toml"]),
+            "--spp expects a number, got many"
+        );
+        assert_eq!(
+            parse_error(&["--spp=-4", "scene.toml"]),
+            "--spp expects a number, got -4"
+        );
+    }
+
+    #[test]
+    fn log_levels() {
+        let command_line = parse_ok(&["-l", "DEBUG", "scene.toml"]);
+        assert_eq!(command_line.log_level.as_deref(), Some("debug"));
+        let command_line = parse_ok(&["--log-level=off", "scene.toml"]);
+        assert_eq!(command_line.log_level.as_deref(), Some("off"));
+        assert_eq!(
+            parse_error(&["-l", "loud", "scene.toml"]),
+            "unknown log level loud"
+        );
+    }
+
+    #[test]
+    fn other_options() {
+        let command_line = parse_ok(&[
+            "-r",
+            "320x240",
+            "--seed",
+            "7",
+            "-d",
+            "out",
+            "--preview",
+            "tev",
+            "scene.toml",
+        ]);
+        assert_eq!(command_line.resolution, Some((320, 240)));
+        assert_eq!(command_line.seed, 7);
+        assert_eq!(command_line.output_directory, Some(PathBuf::from("out")));
+        assert_eq!(command_line.preview, PreviewKind::Tev);
+        assert!(parse_ok(&["-i", "path_tracer_nee", "scene.toml"])
+            .integrator
+            .is_some());
+        assert_eq!(
+            parse_error(&["-r", "320", "scene.toml"]),
+            "-r expects WIDTHxHEIGHT, got 320"
+        );
+    }
+
+    #[test]
+    fn invalid_command_lines() {
+        assert!(matches!(
+            parse(&["-h", "scene.toml"]),
+            Err(CliError::HelpRequested)
+        ));
+        assert_eq!(parse_error(&[]), "no scene file given");
+        assert_eq!(
+            parse_error(&["a.toml", "b.toml"]),
+            "only one scene file can be rendered, got another one: b.toml"
+        );
+        assert_eq!(
+            parse_error(&["--frobnicate", "scene.toml"]),
+            "unknown option --frobnicate"
+        );
+        assert_eq!(
+            parse_error(&["-i", "photon_mapper", "scene.toml"]),
+            "unknown integrator photon_mapper"
+        );
+    }
+}
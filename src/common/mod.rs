@@ -1,8 +1,12 @@
+use crate::lights::Light;
+use crate::materials::Bsdf;
 pub use crate::utilities::mathutils::*;
 pub use log::{info, trace, warn};
+use std::sync::Arc;
 
 pub const EPSILON: fp = 1e-5;
-pub const TILE_SIZE: usize = 256;
+//Default edge length of the square tiles the film is split into, in pixels
+pub const TILE_SIZE: usize = 16;
 
 #[derive(Debug, Clone)]
 pub struct Ray {
@@ -13,11 +17,34 @@ pub struct Ray {
     pub inv_dir: Vec3,
 }
 
+//Part of the film covered by one tile, in pixels from the top left corner. Tiles along the right
+//and bottom edges of the film are smaller when the resolution is not a multiple of the tile size.
+#[derive(Debug, Clone, Copy)]
+pub struct TileBounds {
+    //Position in the order tiles are issued, L -> R and then T -> B
+    pub index: i32,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
 #[derive(Debug, Default)]
 pub struct IntersectionInfo {
     pub t_intersection: fp,
     pub point_of_intersection: Point3,
+    //Geometric normal, for offsetting rays, light emission and telling inside from outside
     pub normal: Vec3,
+    //Normal the BSDF is shaded around, differing from the geometric one under a bump map. On the
+    //same side of the surface as the geometric normal.
+    pub shading_normal: Vec3,
+    pub uv: Point2,
+    //Direction of increasing u along the surface, not normalized. Zero where u has no direction,
+    //such as at the poles of a sphere.
+    pub tangent: Vec3,
+    pub bsdf: Option<Arc<dyn Bsdf>>,
+    //Set when the surface that was hit emits light
+    pub light: Option<Arc<dyn Light>>,
     pub is_aabb: bool,
 }
 
@@ -1,4 +1,7 @@
 use crate::common::*;
+use crate::film::tonemapping::Tonemap;
+
+pub mod tonemapping;
 
 #[derive(Debug, Default, Clone)]
 pub struct Film {
@@ -7,6 +10,9 @@ pub struct Film {
     pub fov: fp,
     pub distance_to_film: fp,
     pub aspect_ratio: fp,
+    pub tonemap: Tonemap,
+    //In stops, radiance is scaled by 2^exposure before tonemapping
+    pub exposure: fp,
 }
 
 impl Film {
@@ -18,4 +24,70 @@ impl Film {
         self.width = width;
         self.height = height;
     }
+
+    //Display value of a pixel for LDR output
+    pub fn tonemap_pixel(&self, radiance: Spectrum) -> Spectrum {
+        self.tonemap.apply(radiance * self.exposure.exp2())
+    }
+}
+
+//Everything the film keeps for a pixel, the rendered radiance plus the AOV layers written to EXR
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FilmPixel {
+    pub radiance: Spectrum,
+    //Albedo, normal and distance of the surfaces the camera sees directly, averaged over all
+    //samples with misses counting as zero
+    pub albedo: Spectrum,
+    pub normal: Vec3,
+    pub depth: fp,
+    //Unbiased sample variance of the radiance samples
+    pub variance: Spectrum,
+}
+
+//What the camera ray of a sample hits first
+pub struct FirstHit {
+    pub albedo: Spectrum,
+    //Facing the camera
+    pub normal: Vec3,
+    pub depth: fp,
+}
+
+//Accumulates the samples of a pixel, the variance with Welford's online algorithm
+#[derive(Default)]
+pub struct PixelAccumulator {
+    samples_count: u32,
+    mean: Spectrum,
+    squared_deviations: Spectrum,
+    albedo: Spectrum,
+    normal: Vec3,
+    depth: fp,
+}
+
+impl PixelAccumulator {
+    pub fn add_sample(&mut self, radiance: Spectrum, first_hit: Option<FirstHit>) {
+        self.samples_count += 1;
+        let delta: Spectrum = radiance - self.mean;
+        self.mean += delta / fp::from(self.samples_count);
+        self.squared_deviations += delta * (radiance - self.mean);
+        if let Some(first_hit) = first_hit {
+            self.albedo += first_hit.albedo;
+            self.normal += first_hit.normal;
+            self.depth += first_hit.depth;
+        }
+    }
+
+    pub fn finish(&self) -> FilmPixel {
+        let samples_count: fp = fp::from(self.samples_count.max(1));
+        FilmPixel {
+            radiance: self.mean,
+            albedo: self.albedo / samples_count,
+            normal: self.normal / samples_count,
+            depth: self.depth / samples_count,
+            variance: if self.samples_count > 1 {
+                self.squared_deviations / (samples_count - 1.0)
+            } else {
+                Spectrum::default()
+            },
+        }
+    }
 }
@@ -0,0 +1,69 @@
+use crate::common::*;
+
+//Operators turning scene radiance into display values in [0, 1] for LDR output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemap {
+    //Clamp only
+    #[default]
+    Linear,
+    Reinhard,
+    //Jim Hejl and Richard Burgess-Dawson's curve, the sRGB response is baked into it
+    Filmic,
+    //Krzysztof Narkowicz's fit of the ACES reference rendering transform
+    Aces,
+}
+
+impl Tonemap {
+    //Tonemap names as used in scene files, "gamma" is Tungsten's name for plain sRGB encoding
+    pub fn from_name(name: &str) -> Option<Tonemap> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" | "gamma" => Some(Tonemap::Linear),
+            "reinhard" => Some(Tonemap::Reinhard),
+            "filmic" => Some(Tonemap::Filmic),
+            "aces" => Some(Tonemap::Aces),
+            _ => None,
+        }
+    }
+
+    //Display encoded value of a radiance value already scaled by the exposure
+    pub fn apply(self, radiance: Spectrum) -> Spectrum {
+        let mapped: Spectrum = match self {
+            Tonemap::Linear => radiance,
+            Tonemap::Reinhard => radiance / (Spectrum::from(1.0) + radiance),
+            Tonemap::Filmic => {
+                let x: Spectrum =
+                    Spectrum::from(0.0).max_component_wise(radiance - Spectrum::from(0.004));
+                return (x * (x * 6.2 + Spectrum::from(0.5)))
+                    / (x * (x * 6.2 + Spectrum::from(1.7)) + Spectrum::from(0.06));
+            }
+            Tonemap::Aces => {
+                (radiance * (radiance * 2.51 + Spectrum::from(0.03)))
+                    / (radiance * (radiance * 2.43 + Spectrum::from(0.59)) + Spectrum::from(0.14))
+            }
+        };
+        Spectrum::new(
+            srgb_oetf(mapped.x),
+            srgb_oetf(mapped.y),
+            srgb_oetf(mapped.z),
+        )
+    }
+}
+
+//Linear to sRGB encoded, clamped to [0, 1]
+pub fn srgb_oetf(value: fp) -> fp {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+//sRGB encoded to linear, the inverse of srgb_oetf
+pub fn srgb_eotf(value: fp) -> fp {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
+use crate::common::*;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+use crate::lights::Light;
+use crate::materials::Bsdf;
+use std::sync::Arc;
+
+//Unit cube centered at the origin
+#[derive(Clone)]
+pub struct Cube {
+    pub bounding_box: AxisAlignedBoundingBox,
+    pub bsdf: Arc<dyn Bsdf>,
+    pub light: Option<Arc<dyn Light>>,
+}
+
+impl Cube {
+    pub fn new(bsdf: Arc<dyn Bsdf>) -> Cube {
+        Cube {
+            bounding_box: AxisAlignedBoundingBox::new_aabb(Point3::from(-0.5), Point3::from(0.5)),
+            bsdf,
+            light: None,
+        }
+    }
+
+    //Point on the given face, where the face index is 2 * axis + (0 for -axis, 1 for +axis)
+    fn point_on_face(face: usize, u: Point2) -> (Point3, Vec3) {
+        let axis = face / 2;
+        let sign: fp = if face.is_multiple_of(2) { -1.0 } else { 1.0 };
+        let mut point: [fp; 3] = [0.0; 3];
+        let mut normal: [fp; 3] = [0.0; 3];
+        point[axis] = 0.5 * sign;
+        point[(axis + 1) % 3] = u.x - 0.5;
+        point[(axis + 2) % 3] = u.y - 0.5;
+        normal[axis] = sign;
+        (
+            Point3::new(point[0], point[1], point[2]),
+            Vec3::new(normal[0], normal[1], normal[2]),
+        )
+    }
+
+    //Each face is mapped to the full uv square, using the two axes that span it
+    fn uv_at(point: Point3, axis: i32) -> Point2 {
+        let u_axis = (axis + 1) % 3;
+        let v_axis = (axis + 2) % 3;
+        Point2::new(point[u_axis] + 0.5, point[v_axis] + 0.5)
+    }
+
+    //Direction of the axis u is taken from
+    fn tangent_at(axis: i32) -> Vec3 {
+        match (axis + 1) % 3 {
+            0 => Vec3::new(1.0, 0.0, 0.0),
+            1 => Vec3::new(0.0, 1.0, 0.0),
+            _ => Vec3::new(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+impl Hitable for Cube {
+    fn check_intersection_and_return_closest_hit(
+        &self,
+        ray: Ray,
+        t_min: fp,
+        t_max: fp,
+    ) -> Option<IntersectionInfo> {
+        //Slab test, keeping both the entry and the exit distance since rays can start inside
+        let mut t_near: fp = fp::MIN;
+        let mut t_far: fp = fp::MAX;
+        for i in 0..3 {
+            let t_1: fp = (-0.5 - ray.o[i]) * ray.inv_dir[i];
+            let t_2: fp = (0.5 - ray.o[i]) * ray.inv_dir[i];
+            t_near = fp::max(t_near, fp::min(t_1, t_2));
+            t_far = fp::min(t_far, fp::max(t_1, t_2));
+        }
+        if t_far < t_near {
+            return None;
+        }
+
+        let t_max: fp = fp::min(t_max, ray.tmax);
+        let t: fp = if t_near > t_min && t_near < t_max {
+            t_near
+        } else if t_far > t_min && t_far < t_max {
+            t_far
+        } else {
+            return None;
+        };
+
+        let p_hit: Point3 = ray.o + ray.d * t;
+        let axis: i32 = p_hit.abs().max_dimension();
+        let mut normal: Vec3 = Vec3::from(0.0);
+        match axis {
+            0 => normal.x = p_hit.x.signum(),
+            1 => normal.y = p_hit.y.signum(),
+            _ => normal.z = p_hit.z.signum(),
+        }
+
+        let intersection_info = IntersectionInfo {
+            t_intersection: t,
+            point_of_intersection: p_hit,
+            normal,
+            shading_normal: normal,
+            uv: Cube::uv_at(p_hit, axis),
+            tangent: Cube::tangent_at(axis),
+            bsdf: Some(self.bsdf.clone()),
+            light: self.light.clone(),
+            is_aabb: false,
+        };
+        Some(intersection_info)
+    }
+}
+
+impl Boundable for Cube {
+    fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl SampleableShape for Cube {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        //All six faces have the same area, pick one and reuse the remainder of u.x on it
+        let scaled: fp = u.x * 6.0;
+        let face: usize = usize::min(scaled as usize, 5);
+        let u_face: Point2 = Point2::new(scaled - face as fp, u.y);
+        let (point, normal) = Cube::point_on_face(face, u_face);
+        SurfaceSample {
+            point,
+            normal,
+            uv: u_face,
+            pdf: 1.0 / 6.0,
+        }
+    }
+
+    fn pdf_surface(&self, _intersection_info: &IntersectionInfo) -> fp {
+        1.0 / 6.0
+    }
+}
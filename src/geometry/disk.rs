@@ -0,0 +1,103 @@
+use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
+use crate::common::*;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+use crate::lights::Light;
+use crate::materials::Bsdf;
+use crate::utilities::sampling::concentric_sample_disk;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+//Disk of unit diameter centered at the origin in the xz plane, facing +y
+#[derive(Clone)]
+pub struct Disk {
+    pub radius: fp,
+    pub bounding_box: AxisAlignedBoundingBox,
+    pub bsdf: Arc<dyn Bsdf>,
+    pub light: Option<Arc<dyn Light>>,
+}
+
+impl Disk {
+    pub fn new(bsdf: Arc<dyn Bsdf>) -> Disk {
+        let radius: fp = 0.5;
+        Disk {
+            radius,
+            bounding_box: AxisAlignedBoundingBox::new_aabb(
+                Point3::new(-radius, 0.0, -radius),
+                Point3::new(radius, 0.0, radius),
+            ),
+            bsdf,
+            light: None,
+        }
+    }
+
+    //Polar coordinates, u along the radius and v around the disk
+    fn uv_at(&self, p: Point3) -> Point2 {
+        let phi: fp = fp::atan2(p.z, p.x);
+        let phi: fp = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let r: fp = (p.x * p.x + p.z * p.z).sqrt();
+        Point2::new(r / self.radius, phi / (2.0 * PI))
+    }
+
+    fn area(&self) -> fp {
+        PI * self.radius * self.radius
+    }
+}
+
+impl Hitable for Disk {
+    fn check_intersection_and_return_closest_hit(
+        &self,
+        ray: Ray,
+        t_min: fp,
+        t_max: fp,
+    ) -> Option<IntersectionInfo> {
+        if ray.d.y == 0.0 {
+            return None;
+        }
+        let t: fp = -ray.o.y / ray.d.y;
+        if !(t > t_min && t < fp::min(t_max, ray.tmax)) {
+            return None;
+        }
+        let p_hit: Point3 = ray.o + ray.d * t;
+        if p_hit.x * p_hit.x + p_hit.z * p_hit.z > self.radius * self.radius {
+            return None;
+        }
+        let p_hit: Point3 = Point3::new(p_hit.x, 0.0, p_hit.z);
+
+        let intersection_info = IntersectionInfo {
+            t_intersection: t,
+            point_of_intersection: p_hit,
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            shading_normal: Vec3::new(0.0, 1.0, 0.0),
+            uv: self.uv_at(p_hit),
+            //u grows along the radius
+            tangent: Vec3::new(p_hit.x, 0.0, p_hit.z),
+            bsdf: Some(self.bsdf.clone()),
+            light: self.light.clone(),
+            is_aabb: false,
+        };
+        Some(intersection_info)
+    }
+}
+
+impl Boundable for Disk {
+    fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl SampleableShape for Disk {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        let d: Point2 = concentric_sample_disk(u) * self.radius;
+        let point: Point3 = Point3::new(d.x, 0.0, d.y);
+        SurfaceSample {
+            point,
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            uv: self.uv_at(point),
+            pdf: 1.0 / self.area(),
+        }
+    }
+
+    fn pdf_surface(&self, _intersection_info: &IntersectionInfo) -> fp {
+        1.0 / self.area()
+    }
+}
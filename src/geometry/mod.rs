@@ -1,5 +1,11 @@
+use crate::accel::aabb::Boundable;
 use crate::common::*;
 
+pub mod cube;
+pub mod disk;
+pub mod quad;
+pub mod sphere;
+pub mod transformed;
 pub mod triangle;
 
 pub trait Hitable: Send + Sync {
@@ -10,3 +16,39 @@ pub trait Hitable: Send + Sync {
         t_max: fp,
     ) -> Option<IntersectionInfo>;
 }
+
+pub struct SurfaceSample {
+    pub point: Point3,
+    pub normal: Vec3,
+    pub uv: Point2,
+    //Density of the sampled point, with respect to surface area
+    pub pdf: fp,
+}
+
+//Shapes that can pick points on their surface, which is what area lights need
+pub trait SampleableShape: Boundable {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample;
+
+    //Area density with which sample_surface picks the point of the given intersection
+    fn pdf_surface(&self, intersection_info: &IntersectionInfo) -> fp;
+
+    //Surface area, estimated from the sampling density on a grid of samples. This is exact for
+    //shapes sampled uniformly by area, shapes that are not can override it.
+    fn area(&self) -> fp {
+        const GRID_SIZE: usize = 16;
+        let mut area: fp = 0.0;
+        for i in 0..GRID_SIZE {
+            for j in 0..GRID_SIZE {
+                let u = Point2::new(
+                    (i as fp + 0.5) / GRID_SIZE as fp,
+                    (j as fp + 0.5) / GRID_SIZE as fp,
+                );
+                let pdf: fp = self.sample_surface(u).pdf;
+                if pdf > 0.0 {
+                    area += 1.0 / pdf;
+                }
+            }
+        }
+        area / (GRID_SIZE * GRID_SIZE) as fp
+    }
+}
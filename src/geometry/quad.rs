@@ -0,0 +1,82 @@
+use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
+use crate::common::*;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+use crate::lights::Light;
+use crate::materials::Bsdf;
+use std::sync::Arc;
+
+//Unit square centered at the origin in the xz plane, facing +y, same as Tungsten's quad before its transform
+#[derive(Clone)]
+pub struct Quad {
+    pub bounding_box: AxisAlignedBoundingBox,
+    pub bsdf: Arc<dyn Bsdf>,
+    pub light: Option<Arc<dyn Light>>,
+}
+
+impl Quad {
+    pub fn new(bsdf: Arc<dyn Bsdf>) -> Quad {
+        Quad {
+            bounding_box: AxisAlignedBoundingBox::new_aabb(
+                Point3::new(-0.5, 0.0, -0.5),
+                Point3::new(0.5, 0.0, 0.5),
+            ),
+            bsdf,
+            light: None,
+        }
+    }
+}
+
+impl Hitable for Quad {
+    fn check_intersection_and_return_closest_hit(
+        &self,
+        ray: Ray,
+        t_min: fp,
+        t_max: fp,
+    ) -> Option<IntersectionInfo> {
+        if ray.d.y == 0.0 {
+            return None;
+        }
+        let t: fp = -ray.o.y / ray.d.y;
+        if !(t > t_min && t < fp::min(t_max, ray.tmax)) {
+            return None;
+        }
+        let p_hit: Point3 = ray.o + ray.d * t;
+        if fp::abs(p_hit.x) > 0.5 || fp::abs(p_hit.z) > 0.5 {
+            return None;
+        }
+
+        let intersection_info = IntersectionInfo {
+            t_intersection: t,
+            point_of_intersection: Point3::new(p_hit.x, 0.0, p_hit.z),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            shading_normal: Vec3::new(0.0, 1.0, 0.0),
+            uv: Point2::new(p_hit.x + 0.5, p_hit.z + 0.5),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            bsdf: Some(self.bsdf.clone()),
+            light: self.light.clone(),
+            is_aabb: false,
+        };
+        Some(intersection_info)
+    }
+}
+
+impl Boundable for Quad {
+    fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl SampleableShape for Quad {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        SurfaceSample {
+            point: Point3::new(u.x - 0.5, 0.0, u.y - 0.5),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            uv: u,
+            pdf: 1.0,
+        }
+    }
+
+    fn pdf_surface(&self, _intersection_info: &IntersectionInfo) -> fp {
+        1.0
+    }
+}
@@ -0,0 +1,112 @@
+use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
+use crate::common::*;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+use crate::lights::Light;
+use crate::materials::Bsdf;
+use crate::utilities::sampling::uniform_sample_sphere;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Sphere {
+    pub center: Point3,
+    pub radius: fp,
+    pub bounding_box: AxisAlignedBoundingBox,
+    pub bsdf: Arc<dyn Bsdf>,
+    pub light: Option<Arc<dyn Light>>,
+}
+
+impl Sphere {
+    pub fn new(center: Point3, radius: fp, bsdf: Arc<dyn Bsdf>) -> Sphere {
+        let extent: Vec3 = Vec3::from(radius);
+        Sphere {
+            center,
+            radius,
+            bounding_box: AxisAlignedBoundingBox::new_aabb(center - extent, center + extent),
+            bsdf,
+            light: None,
+        }
+    }
+
+    //Spherical coordinates of a point on the sphere mapped to [0,1]^2, with the poles along y
+    fn uv_at(&self, normal: Vec3) -> Point2 {
+        let phi: fp = fp::atan2(normal.z, normal.x);
+        let phi: fp = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let theta: fp = fp::acos(normal.y.clamp(-1.0, 1.0));
+        Point2::new(phi / (2.0 * PI), theta / PI)
+    }
+}
+
+impl Hitable for Sphere {
+    fn check_intersection_and_return_closest_hit(
+        &self,
+        ray: Ray,
+        t_min: fp,
+        t_max: fp,
+    ) -> Option<IntersectionInfo> {
+        //Solve |o + td - c|^2 = r^2 for t
+        let oc: Vec3 = ray.o - self.center;
+        let a: fp = ray.d.length_squared();
+        let half_b: fp = oc.dot(ray.d);
+        let c: fp = oc.length_squared() - self.radius * self.radius;
+        let discriminant: fp = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        //Stable quadratic roots, see "Precision Improvements for Ray/Sphere Intersection" in Ray Tracing Gems
+        let sqrt_discriminant: fp = discriminant.sqrt();
+        let q: fp = -(half_b + sqrt_discriminant.copysign(half_b));
+        let (mut t_0, mut t_1) = (q / a, c / q);
+        if t_0 > t_1 {
+            std::mem::swap(&mut t_0, &mut t_1);
+        }
+
+        let t_max: fp = fp::min(t_max, ray.tmax);
+        let t: fp = if t_0 > t_min && t_0 < t_max {
+            t_0
+        } else if t_1 > t_min && t_1 < t_max {
+            t_1
+        } else {
+            return None;
+        };
+
+        let p_hit: Point3 = ray.o + ray.d * t;
+        let normal: Vec3 = ((p_hit - self.center) / self.radius).normalize();
+        let intersection_info = IntersectionInfo {
+            t_intersection: t,
+            point_of_intersection: p_hit,
+            normal,
+            shading_normal: normal,
+            uv: self.uv_at(normal),
+            //u follows phi around the y axis
+            tangent: Vec3::new(-normal.z, 0.0, normal.x),
+            bsdf: Some(self.bsdf.clone()),
+            light: self.light.clone(),
+            is_aabb: false,
+        };
+        Some(intersection_info)
+    }
+}
+
+impl Boundable for Sphere {
+    fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl SampleableShape for Sphere {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        let normal: Vec3 = uniform_sample_sphere(u);
+        SurfaceSample {
+            point: self.center + normal * self.radius,
+            normal,
+            uv: self.uv_at(normal),
+            pdf: 1.0 / (4.0 * PI * self.radius * self.radius),
+        }
+    }
+
+    fn pdf_surface(&self, _intersection_info: &IntersectionInfo) -> fp {
+        1.0 / (4.0 * PI * self.radius * self.radius)
+    }
+}
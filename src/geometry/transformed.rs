@@ -0,0 +1,124 @@
+use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
+use crate::common::*;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+
+//Places a shape defined in its own object space into the world. Rays are moved into object space
+//instead of moving the shape, the direction is not renormalized so hit distances stay the same.
+#[derive(Clone)]
+pub struct TransformedShape<T> {
+    pub shape: T,
+    object_to_world: Transform,
+    bounding_box: AxisAlignedBoundingBox,
+}
+
+impl<T: Boundable> TransformedShape<T> {
+    pub fn new(shape: T, object_to_world: Transform) -> TransformedShape<T> {
+        //World space box around the 8 transformed corners of the object space box
+        let object_box: AxisAlignedBoundingBox = shape.get_bounding_box();
+        let bounds: [Point3; 2] = [object_box.min, object_box.max];
+        let mut min: Point3 = Point3::from(fp::INFINITY);
+        let mut max: Point3 = Point3::from(fp::NEG_INFINITY);
+        for corner in 0..8 {
+            let p: Point3 = object_to_world.transform_point(Point3::new(
+                bounds[corner & 1].x,
+                bounds[(corner >> 1) & 1].y,
+                bounds[corner >> 2].z,
+            ));
+            min = Point3::new(
+                fp::min(min.x, p.x),
+                fp::min(min.y, p.y),
+                fp::min(min.z, p.z),
+            );
+            max = max.max_component_wise(p);
+        }
+        TransformedShape {
+            shape,
+            object_to_world,
+            bounding_box: AxisAlignedBoundingBox::new_aabb(min, max),
+        }
+    }
+
+    //Ratio of a world space area element to the object space one at a point with the given object
+    //space normal, |det M| * |M^-T n|
+    fn area_scale(&self, object_normal: Vec3) -> fp {
+        self.object_to_world.determinant().abs()
+            * self
+                .object_to_world
+                .transform_normal(object_normal.normalize())
+                .length()
+    }
+}
+
+impl<T: Hitable> Hitable for TransformedShape<T> {
+    fn check_intersection_and_return_closest_hit(
+        &self,
+        ray: Ray,
+        t_min: fp,
+        t_max: fp,
+    ) -> Option<IntersectionInfo> {
+        let world_to_object: Transform = self.object_to_world.inverse();
+        let object_ray = Ray::new(
+            world_to_object.transform_point(ray.o),
+            world_to_object.transform_vector(ray.d),
+            ray.t,
+            ray.tmax,
+        );
+        let mut intersection_info: IntersectionInfo = self
+            .shape
+            .check_intersection_and_return_closest_hit(object_ray, t_min, t_max)?;
+        intersection_info.point_of_intersection = self
+            .object_to_world
+            .transform_point(intersection_info.point_of_intersection);
+        intersection_info.normal = self
+            .object_to_world
+            .transform_normal(intersection_info.normal)
+            .normalize();
+        intersection_info.shading_normal = self
+            .object_to_world
+            .transform_normal(intersection_info.shading_normal)
+            .normalize();
+        intersection_info.tangent = self
+            .object_to_world
+            .transform_vector(intersection_info.tangent);
+        Some(intersection_info)
+    }
+}
+
+impl<T: Boundable> Boundable for TransformedShape<T> {
+    fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl<T: SampleableShape> SampleableShape for TransformedShape<T> {
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        let object_sample: SurfaceSample = self.shape.sample_surface(u);
+        SurfaceSample {
+            point: self.object_to_world.transform_point(object_sample.point),
+            normal: self
+                .object_to_world
+                .transform_normal(object_sample.normal)
+                .normalize(),
+            uv: object_sample.uv,
+            pdf: object_sample.pdf / self.area_scale(object_sample.normal),
+        }
+    }
+
+    fn pdf_surface(&self, intersection_info: &IntersectionInfo) -> fp {
+        //World normals are M^-T applied to object normals, so M^T takes them back
+        let object_normal: Vec3 = self
+            .object_to_world
+            .inverse()
+            .transform_normal(intersection_info.normal);
+        let object_intersection_info = IntersectionInfo {
+            point_of_intersection: self
+                .object_to_world
+                .inverse()
+                .transform_point(intersection_info.point_of_intersection),
+            normal: object_normal.normalize(),
+            uv: intersection_info.uv,
+            ..Default::default()
+        };
+        self.shape.pdf_surface(&object_intersection_info) / self.area_scale(object_normal)
+    }
+}
@@ -1,7 +1,11 @@
 use crate::accel::aabb::{AxisAlignedBoundingBox, Boundable};
 use crate::common::*;
-use crate::geometry::Hitable;
+use crate::geometry::{Hitable, SampleableShape, SurfaceSample};
+use crate::lights::Light;
+use crate::materials::Bsdf;
+use crate::textures::bumpmap::BumpMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 pub struct TriangleMesh {
     //Same as tobj::Mesh
@@ -21,14 +25,30 @@ pub struct Triangle {
     pub normals: Vec<Vec3>,
     pub texture_coordinates: Vec<Point2>,
     pub bounding_box: AxisAlignedBoundingBox,
+    pub bsdf: Arc<dyn Bsdf>,
+    //Set when the triangle belongs to an emissive mesh
+    pub light: Option<Arc<dyn Light>>,
+    //Perturbs the normal seen by shading, from the mesh's material
+    pub bump_map: Option<Arc<BumpMap>>,
 }
 
 impl TriangleMesh {
-    pub fn new(mesh_name_and_path: PathBuf) -> Vec<TriangleMesh> {
+    //Also returns the materials of the .mtl files the .obj refers to, which the meshes' material_id
+    //index into. A missing or broken .mtl file only loses the materials.
+    pub fn new(
+        mesh_name_and_path: PathBuf,
+    ) -> Result<(Vec<TriangleMesh>, Vec<tobj::Material>), tobj::LoadError> {
         //Load in the .obj file. It might have multiple models(meshes) in it
-        let obj_mesh = tobj::load_obj(mesh_name_and_path.as_path(), &tobj::LoadOptions::default());
-        assert!(obj_mesh.is_ok());
-        let (models, _materials) = obj_mesh.unwrap();
+        let (models, materials) =
+            tobj::load_obj(mesh_name_and_path.as_path(), &tobj::LoadOptions::default())?;
+        let materials = materials.unwrap_or_else(|error| {
+            warn!(
+                "Warning: could not load the materials of {}: {}",
+                mesh_name_and_path.display(),
+                error
+            );
+            vec![]
+        });
         let mut meshes: Vec<TriangleMesh> = Vec::new();
         for model in models {
             let mesh = TriangleMesh {
@@ -41,10 +61,32 @@ impl TriangleMesh {
             meshes.push(mesh);
         }
 
-        meshes
+        Ok((meshes, materials))
+    }
+
+    //Bake the transform of the scene entry into the vertex data
+    pub fn apply_transform(&mut self, transform: &Transform) {
+        for position in self.positions.chunks_exact_mut(3) {
+            let p: Point3 = transform.transform_point(Point3::new(
+                fp::from(position[0]),
+                fp::from(position[1]),
+                fp::from(position[2]),
+            ));
+            position.copy_from_slice(&[p.x as f32, p.y as f32, p.z as f32]);
+        }
+        for normal in self.normals.chunks_exact_mut(3) {
+            let n: Vec3 = transform
+                .transform_normal(Vec3::new(
+                    fp::from(normal[0]),
+                    fp::from(normal[1]),
+                    fp::from(normal[2]),
+                ))
+                .normalize();
+            normal.copy_from_slice(&[n.x as f32, n.y as f32, n.z as f32]);
+        }
     }
 
-    pub fn get_triangles_from_mesh(&self) -> Vec<Triangle> {
+    pub fn get_triangles_from_mesh(&self, bsdf: Arc<dyn Bsdf>) -> Vec<Triangle> {
         let mut triangles: Vec<Triangle> = vec![];
 
         //*self.positions.get(index[0] as usize).unwrap() as fp
@@ -108,6 +150,9 @@ impl TriangleMesh {
                 ],
 
                 bounding_box: AxisAlignedBoundingBox::default(),
+                bsdf: bsdf.clone(),
+                light: None,
+                bump_map: None,
             };
             triangle.bounding_box = Triangle::set_bounding_box(&triangle);
             // info!("AABB of triangle: {:?}", triangle.bounding_box);
@@ -240,26 +285,32 @@ impl Hitable for Triangle {
             );
         } else {
             let inv_det_uv: fp = 1.0 / determinant;
-            dpdu = (dp02 * duv12.y - dp12 * duv02.x) * inv_det_uv;
-            dpdv = (dp02 * -duv12.y + dp12 * duv02.x) * inv_det_uv;
-            // To remove compiler warning of unused variable
-            let _dpdu = dpdu;
-            let _dpdv = dpdv;
+            dpdu = (dp02 * duv12.y - dp12 * duv02.y) * inv_det_uv;
+            dpdv = (dp12 * duv02.x - dp02 * duv12.x) * inv_det_uv;
         }
 
         //8. Find point of intersection and texture coordinates at given point
         let p_hit: Point3 =
             self.positions[0] * b0 + self.positions[1] * b1 + self.positions[2] * b2;
-        let _uv_hit: Point2 = self.texture_coordinates[0] * b0
+        let uv_hit: Point2 = self.texture_coordinates[0] * b0
             + self.texture_coordinates[1] * b1
             + self.texture_coordinates[2] * b2;
         let mut geometric_normal: Vector3 = dp02.cross(dp12).normalize();
         geometric_normal.face_outward_normal(self.normals[0]);
+        let shading_normal: Vector3 = match &self.bump_map {
+            Some(bump_map) => bump_map.shading_normal(uv_hit, geometric_normal, dpdu, dpdv),
+            None => geometric_normal,
+        };
 
         let intersection_info = IntersectionInfo {
             t_intersection: t,
             point_of_intersection: p_hit,
             normal: geometric_normal,
+            shading_normal,
+            uv: uv_hit,
+            tangent: dpdu,
+            bsdf: Some(self.bsdf.clone()),
+            light: self.light.clone(),
             is_aabb: false,
         };
         Some(intersection_info)
@@ -302,8 +353,128 @@ impl Triangle {
         AxisAlignedBoundingBox::new_aabb(min_point, max_point)
     }
 }
+impl SampleableShape for Triangle {
+    //Uniform over the area of the triangle
+    fn sample_surface(&self, u: Point2) -> SurfaceSample {
+        let su0: fp = u.x.sqrt();
+        let b0: fp = 1.0 - su0;
+        let b1: fp = u.y * su0;
+        let b2: fp = 1.0 - b0 - b1;
+        //Same orientation as the normal reported by intersections
+        let mut normal: Vec3 = (self.positions[0] - self.positions[2])
+            .cross(self.positions[1] - self.positions[2])
+            .normalize();
+        normal.face_outward_normal(self.normals[0]);
+        SurfaceSample {
+            point: self.positions[0] * b0 + self.positions[1] * b1 + self.positions[2] * b2,
+            normal,
+            uv: self.texture_coordinates[0] * b0
+                + self.texture_coordinates[1] * b1
+                + self.texture_coordinates[2] * b2,
+            pdf: 1.0 / self.area(),
+        }
+    }
+
+    fn pdf_surface(&self, _intersection_info: &IntersectionInfo) -> fp {
+        1.0 / self.area()
+    }
+
+    fn area(&self) -> fp {
+        0.5 * (self.positions[1] - self.positions[0])
+            .cross(self.positions[2] - self.positions[0])
+            .length()
+    }
+}
+
 impl Boundable for Triangle {
     fn get_bounding_box(&self) -> AxisAlignedBoundingBox {
         self.bounding_box.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrators::offset_ray_origin;
+    use crate::materials::lambert::Lambert;
+    use crate::textures::constanttexture::ConstantTexture;
+    use crate::textures::Texture;
+
+    //Height rising steeply along u
+    #[derive(Debug)]
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn evaluate(&self, uv: Point2) -> Spectrum {
+            Spectrum::from(2.0 * uv.x)
+        }
+
+        fn average(&self) -> Spectrum {
+            Spectrum::from(1.0)
+        }
+    }
+
+    fn bumped_triangle() -> Triangle {
+        let up: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+        let mut triangle = Triangle {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+            normals: vec![up, up, up],
+            texture_coordinates: vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 0.0),
+                Point2::new(0.0, 1.0),
+            ],
+            bounding_box: AxisAlignedBoundingBox::default(),
+            bsdf: Arc::new(Lambert::new(Arc::new(ConstantTexture::new(
+                Spectrum::from(0.5),
+            )))),
+            light: None,
+            bump_map: Some(Arc::new(BumpMap::new(Arc::new(Ramp), 1.0))),
+        };
+        triangle.bounding_box = Triangle::set_bounding_box(&triangle);
+        triangle
+    }
+
+    #[test]
+    fn bump_maps_leave_the_geometric_normal_alone() {
+        let triangle: Triangle = bumped_triangle();
+        let ray = Ray::new(
+            Point3::new(0.25, 1.0, 0.25),
+            Vec3::new(0.0, -1.0, 0.0),
+            0.0,
+            fp::INFINITY,
+        );
+        let hit = triangle
+            .check_intersection_and_return_closest_hit(ray, 0.0, fp::INFINITY)
+            .unwrap();
+        assert!(hit.normal.y > 0.999);
+        assert!(hit.shading_normal.y < 0.5 && hit.shading_normal.x < -0.8);
+    }
+
+    //A direction above the surface but behind the bumped normal has to leave from above the surface
+    #[test]
+    fn rays_leaving_a_bumped_triangle_do_not_hit_it_again() {
+        let triangle: Triangle = bumped_triangle();
+        let ray = Ray::new(
+            Point3::new(0.25, 1.0, 0.25),
+            Vec3::new(0.0, -1.0, 0.0),
+            0.0,
+            fp::INFINITY,
+        );
+        let hit = triangle
+            .check_intersection_and_return_closest_hit(ray, 0.0, fp::INFINITY)
+            .unwrap();
+        let wi: Vec3 = Vec3::new(1.0, 0.2, 0.0).normalize();
+        assert!(wi.dot(hit.shading_normal) < 0.0);
+
+        let origin: Point3 = offset_ray_origin(hit.point_of_intersection, hit.normal, wi);
+        let outgoing = Ray::new(origin, wi, 0.0, fp::INFINITY);
+        assert!(triangle
+            .check_intersection_and_return_closest_hit(outgoing, 0.0, fp::INFINITY)
+            .is_none());
+    }
+}
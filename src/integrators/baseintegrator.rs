@@ -1,112 +1,170 @@
 use crate::accel::aabb::Boundable;
 use crate::common::*;
-use crate::film::Film;
+use crate::film::{Film, FilmPixel, PixelAccumulator};
 pub use crate::integrators::directlighting;
 use crate::integrators::directlighting::DirectLightingIntegrator;
-use crate::integrators::Integrator;
-use tev_client::TevClient;
+use crate::integrators::pathtracerbsdf::PathTracerBsdfIntegrator;
+use crate::integrators::pathtracernee::PathTracerNeeIntegrator;
+use crate::integrators::{checked_sample, first_hit, tile_rng, Integrator};
+use crate::preview::PreviewSink;
 
-use crate::{SceneCamera, SceneConfig};
+use crate::{SceneCamera, SceneConfig, SceneLights};
 
 use ndarray::parallel::prelude::*;
-use ndarray::Array2;
+use ndarray::{Array2, ArrayViewMut2, Axis};
+use rand::rngs::StdRng;
+use rand::Rng;
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 pub struct BaseIntegrator;
 
+//Radiance arriving along a camera ray, the only part that differs between integrators. Takes the
+//ray, the scene, its geometry and lights, the valid ray interval and the tile's random numbers.
+type RadianceEstimate =
+    fn(Ray, &SceneConfig, &dyn Boundable, &SceneLights, fp, fp, &mut StdRng) -> Spectrum;
+
+#[derive(Debug, Clone, Copy)]
 pub enum Integrators {
     DirectLighting,
     PathTracerBsdf,
     PathTracerNee,
 }
 
-#[allow(dead_code)]
-//TODO: Move Window class and this method to its separate file
-fn from_u8_rgb(r: u8, g: u8, b: u8) -> u32 {
-    let (r, g, b) = (r as u32, g as u32, b as u32);
-    (r << 16) | (g << 8) | b
-}
-
-#[allow(dead_code)]
-fn do_tonemapping(input: Vec<Spectrum>) -> Vec<u32> {
-    let mut ret: Vec<u32> = Vec::with_capacity(input.len());
-    for element in &input {
-        let mut x: Spectrum = *element * 16.0; // Hardcoded Exposure Adjustment
-        x = Spectrum::from(0.0).max_component_wise(x - Spectrum::from(0.004));
-        let ret_color: Spectrum = (x * (Spectrum::from(6.2) * x + Spectrum::from(0.5)))
-            / (x * (Spectrum::from(6.2) * x + Spectrum::from(1.7)) + Spectrum::from(0.06));
-        let gamma_corrected_color: Spectrum = Spectrum::new(
-            ret_color.x.powf(2.20),
-            ret_color.y.powf(2.20),
-            ret_color.z.powf(2.20),
-        );
-        let final_color: u32 = from_u8_rgb(
-            (gamma_corrected_color.x * 255.0) as u8,
-            (gamma_corrected_color.y * 255.0) as u8,
-            (gamma_corrected_color.z * 255.0) as u8,
-        );
-        ret.push(final_color);
+impl Integrators {
+    //Integrator type names as used in scene files
+    pub fn from_name(name: &str) -> Option<Integrators> {
+        match name.to_ascii_lowercase().as_str() {
+            "direct_lighting" => Some(Integrators::DirectLighting),
+            "path_tracer_bsdf" => Some(Integrators::PathTracerBsdf),
+            "path_tracer_nee" => Some(Integrators::PathTracerNee),
+            _ => None,
+        }
     }
-    ret
 }
 
 impl Integrator for BaseIntegrator {
     fn render(
         scene: Arc<SceneConfig>,
-        samples_count: u32,
-        bounces_count: u32,
         camera: Arc<SceneCamera>,
         geometries: Arc<dyn Boundable>,
         //geometries: Arc<SceneGeometries>,
+        lights: Arc<SceneLights>,
         film: Arc<Film>,
-        t_min: fp,
         t_max: fp,
-        tev_client: Arc<Mutex<TevClient>>,
-    ) -> Array2<Spectrum> {
-        let scene_data: Vec<Spectrum> = vec![
-            Vector3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0
-            };
-            (film.height * film.width) as usize
-        ];
+        preview: Arc<dyn PreviewSink>,
+    ) -> Array2<FilmPixel> {
+        let scene_data: Vec<FilmPixel> =
+            vec![FilmPixel::default(); (film.height * film.width) as usize];
         let mut frame_buffer2 =
             Array2::from_shape_vec((film.height as usize, film.width as usize), scene_data)
                 .unwrap();
-        let tiles: Vec<_> = frame_buffer2
-            .exact_chunks_mut([16, 16])
-            .into_iter()
-            .collect();
-
-        tiles
-            .into_par_iter()
+        //Cut the film into rows of tiles, then each row into tiles. The last row and column get
+        //whatever is left over.
+        let tile_size: usize = scene.tile_size;
+        let mut tiles: Vec<(TileBounds, ArrayViewMut2<'_, FilmPixel>)> = vec![];
+        for (row, mut tile_row) in frame_buffer2
+            .axis_chunks_iter_mut(Axis(0), tile_size)
             .enumerate()
-            .for_each(|(i, tile)| match scene.integrator {
-                Integrators::DirectLighting => {
-                    let camera = camera.clone();
-                    let geometries = geometries.clone();
-                    let film = film.clone();
-                    let tev_client = tev_client.clone();
-                    DirectLightingIntegrator::integrate(
-                        tile,
-                        i as i32,
-                        samples_count,
-                        bounces_count,
-                        camera,
-                        geometries,
-                        film,
-                        t_min,
-                        t_max,
-                        tev_client,
-                    );
-                }
-                Integrators::PathTracerBsdf => {}
-                Integrators::PathTracerNee => {}
-            });
+        {
+            let mut x: usize = 0;
+            while tile_row.ncols() > 0 {
+                let width: usize = usize::min(tile_size, tile_row.ncols());
+                let (tile, rest) = tile_row.split_at(Axis(1), width);
+                tiles.push((
+                    TileBounds {
+                        index: tiles.len() as i32,
+                        x: x as i32,
+                        y: (row * tile_size) as i32,
+                        width: tile.ncols() as i32,
+                        height: tile.nrows() as i32,
+                    },
+                    tile,
+                ));
+                x += tile_size;
+                tile_row = rest;
+            }
+        }
+
+        let li: RadianceEstimate = match scene.integrator {
+            Integrators::DirectLighting => DirectLightingIntegrator::li,
+            Integrators::PathTracerBsdf => PathTracerBsdfIntegrator::li,
+            Integrators::PathTracerNee => PathTracerNeeIntegrator::li,
+        };
+        tiles.into_par_iter().for_each(|(bounds, tile)| {
+            BaseIntegrator::integrate_tile(
+                tile,
+                bounds,
+                &scene,
+                &camera,
+                geometries.as_ref(),
+                &lights,
+                &film,
+                t_max,
+                preview.as_ref(),
+                li,
+            )
+        });
         //info!("frame_buffer2: {:?}", frame_buffer2);
 
         frame_buffer2
     }
 }
+
+impl BaseIntegrator {
+    //Renders every pixel of a tile with the given radiance estimate, then hands the tile to the
+    //preview
+    #[allow(clippy::too_many_arguments)]
+    fn integrate_tile(
+        mut curr_tile: ArrayViewMut2<'_, FilmPixel>,
+        tile: TileBounds,
+        scene: &SceneConfig,
+        camera: &SceneCamera,
+        geometries: &dyn Boundable,
+        lights: &SceneLights,
+        film: &Film,
+        t_max: fp,
+        preview: &dyn PreviewSink,
+        li: RadianceEstimate,
+    ) {
+        let settings = &scene.settings;
+        let t_min: fp = settings.t_min;
+        let mut rng = tile_rng(scene.seed, &tile);
+        let mut pixel_values_for_viewer: Vec<f32> = vec![];
+        for y_local in 0..tile.height {
+            for x_local in 0..tile.width {
+                let y = tile.y + y_local;
+                let x = tile.x + x_local;
+
+                let mut pixel = PixelAccumulator::default();
+                for _j in 0..settings.samples_count {
+                    //Jitter the sample position inside the pixel
+                    let pixel_sample = Point2::new(rng.gen(), rng.gen());
+                    let ray = camera.generate_camera_ray(x, y, pixel_sample, film);
+                    let first_hit = first_hit(&ray, geometries, t_min, t_max);
+                    let radiance: Spectrum =
+                        li(ray, scene, geometries, lights, t_min, t_max, &mut rng);
+                    pixel.add_sample(checked_sample(radiance, x, y, settings), first_hit);
+                }
+                let film_pixel: FilmPixel = pixel.finish();
+                let pixel_value: Spectrum = film_pixel.radiance;
+                if !pixel_value.x.is_finite()
+                    || !pixel_value.y.is_finite()
+                    || !pixel_value.z.is_finite()
+                {
+                    warn!(
+                        "Value is infinite or NaN!! {} {} {} at pixel {} {}",
+                        pixel_value.x, pixel_value.y, pixel_value.z, x, y
+                    );
+                }
+                pixel_values_for_viewer.push(pixel_value.x as f32);
+                pixel_values_for_viewer.push(pixel_value.y as f32);
+                pixel_values_for_viewer.push(pixel_value.z as f32);
+
+                curr_tile[[y_local as usize, x_local as usize]] = film_pixel;
+            }
+        }
+        // Write tile here to framebuffer for viewer if any
+        preview.update_tile(&tile, &pixel_values_for_viewer);
+    }
+}
@@ -1,106 +1,118 @@
 use crate::accel::aabb::Boundable;
 use crate::common::*;
-use crate::film::Film;
-use crate::SceneCamera;
-use ndarray::ArrayViewMut2;
-use std::borrow::Borrow;
-use std::sync::Arc;
-use std::sync::Mutex;
-use tev_client::PacketUpdateImage;
-use tev_client::TevClient;
+use crate::integrators::{offset_ray_origin, sample_one_light, shading_frame_at};
+use crate::materials::{Bsdf, BsdfSample};
+use crate::utilities::sampling::power_heuristic;
+use crate::{SceneConfig, SceneLights};
+use rand::rngs::StdRng;
+use rand::Rng;
 
 pub struct DirectLightingIntegrator;
 
 impl DirectLightingIntegrator {
-    #[allow(clippy::too_many_arguments)]
-    pub fn integrate(
-        mut curr_tile: ArrayViewMut2<'_, Spectrum>,
-        tile_id: i32,
-        samples_count: u32,
-        bounces_count: u32,
-        camera: Arc<SceneCamera>,
-        geometries: Arc<dyn Boundable>,
-        //geometries: Arc<SceneGeometries>,
-        film: Arc<Film>,
+    //One-bounce direct illumination: emission seen along the camera ray plus light reflected
+    //once at the first surface, estimated by combining light and BSDF sampling
+    pub(crate) fn li(
+        camera_ray: Ray,
+        scene: &SceneConfig,
+        geometries: &dyn Boundable,
+        lights: &SceneLights,
         t_min: fp,
         t_max: fp,
-        tev_client: Arc<Mutex<TevClient>>,
-    ) {
-        let film = film.as_ref().borrow();
-        let mut pixel_values_for_viewer: Vec<f32> = vec![];
-        // Tile ID is issued in L -> R and then T -> B order, starting with 0 from top left corner
-        let x_offset: i32 = tile_id / (film.width / 16);
-        let y_offset: i32 = tile_id % (film.width / 16);
+        rng: &mut StdRng,
+    ) -> Spectrum {
+        let intersection =
+            geometries.check_intersection_and_return_closest_hit(camera_ray.clone(), t_min, t_max);
+        let intersection_info = match intersection {
+            Some(intersection_info) => intersection_info,
+            None => {
+                let mut background: Spectrum = Spectrum::default();
+                for light in lights.infinite_lights() {
+                    background += light.le(&camera_ray);
+                }
+                return background;
+            }
+        };
 
-        let x_starting_offset: i32 = y_offset * 16;
-        let y_starting_offset: i32 = x_offset * 16;
+        //Emitters seen directly
+        let mut radiance: Spectrum = match &intersection_info.light {
+            Some(light) => light.l(&intersection_info, -camera_ray.d),
+            None => Spectrum::default(),
+        };
 
-        for x_local in 0..16 {
-            for y_local in 0..16 {
-                let y = x_offset * 16 + x_local;
-                let x = y_offset * 16 + y_local;
+        let bsdf: &dyn Bsdf = match intersection_info.bsdf.as_deref() {
+            Some(bsdf) => bsdf,
+            None => return radiance,
+        };
 
-                let mut pixel_value: Spectrum = Spectrum::default();
-                for _j in 0..samples_count {
-                    for _k in 0..bounces_count {
-                        //Core Integrator code goes here
-                        let ray = camera.generate_camera_ray(x, y, film);
-                        //info!("Ray info: {:?}", &ray);
-                        let intersection = geometries.check_intersection_and_return_closest_hit(
-                            ray.clone(),
-                            t_min,
-                            t_max,
-                        );
-                        match intersection {
-                            Some(intersection_info) => {
-                                pixel_value += intersection_info.normal;
-                                //info!("{:?}", pixel_value);
-                            }
-                            None => {
-                                pixel_value += Vector3::new(0.5, 0.5, 0.5);
-                            }
-                        }
-                    }
+        //Two-sided shading: shade on the side the ray arrived from, one-sided surfaces are black
+        //from behind. Transmissive BSDFs keep the true normal to tell inside from outside.
+        let wo: Vec3 = -camera_ray.d;
+        let mut normal: Vec3 = intersection_info.normal;
+        if !bsdf.has_transmission() {
+            if normal.dot(wo) < 0.0 && !scene.settings.enable_two_sided_shading {
+                return radiance;
+            }
+            normal.face_outward_normal(wo);
+        }
+        let shading_frame: Frame = shading_frame_at(&intersection_info, normal);
+        let wo_local: Vec3 = shading_frame.to_local(wo);
+        let point: Point3 = intersection_info.point_of_intersection;
+        let uv: Point2 = intersection_info.uv;
+
+        //Light sampling
+        radiance += sample_one_light(
+            point,
+            normal,
+            &shading_frame,
+            wo_local,
+            bsdf,
+            uv,
+            geometries,
+            lights,
+            t_min,
+            rng,
+        );
+
+        //BSDF sampling
+        if lights.lights.is_empty() {
+            return radiance;
+        }
+        let bsdf_sample =
+            match bsdf.sample(wo_local, uv, rng.gen(), Point2::new(rng.gen(), rng.gen())) {
+                Some(bsdf_sample) if bsdf_sample.pdf > 0.0 && !bsdf_sample.f.is_black() => {
+                    bsdf_sample
                 }
-                pixel_value /= fp::from(samples_count);
-                if !pixel_value.x.is_finite()
-                    || !pixel_value.y.is_finite()
-                    || !pixel_value.z.is_finite()
-                {
-                    warn!(
-                        "Value is infinite or NaN!! {} {} {} at pixel {} {}",
-                        pixel_value.x, pixel_value.y, pixel_value.z, x, y
-                    );
+                _ => return radiance,
+            };
+        let wi: Vec3 = shading_frame.to_world(bsdf_sample.wi);
+        let bsdf_ray = Ray::new(offset_ray_origin(point, normal, wi), wi, t_min, t_max);
+        let scattering_weight: Spectrum =
+            bsdf_sample.f * (bsdf_sample.wi.z.abs() / bsdf_sample.pdf);
+        match geometries.check_intersection_and_return_closest_hit(bsdf_ray.clone(), t_min, t_max) {
+            Some(light_intersection_info) => {
+                if let Some(light) = &light_intersection_info.light {
+                    let light_pdf: fp = light.pdf_li(bsdf_ray.o, wi) * lights.selection_pdf(light);
+                    let weight: fp = bsdf_weight(&bsdf_sample, light_pdf);
+                    radiance += scattering_weight * light.l(&light_intersection_info, -wi) * weight;
+                }
+            }
+            None => {
+                for light in lights.infinite_lights() {
+                    let light_pdf: fp = light.pdf_li(bsdf_ray.o, wi) * lights.selection_pdf(light);
+                    let weight: fp = bsdf_weight(&bsdf_sample, light_pdf);
+                    radiance += scattering_weight * light.le(&bsdf_ray) * weight;
                 }
-                pixel_values_for_viewer.push(pixel_value.x as f32);
-                pixel_values_for_viewer.push(pixel_value.y as f32);
-                pixel_values_for_viewer.push(pixel_value.z as f32);
-
-                curr_tile[[x_local as usize, y_local as usize]] = pixel_value;
             }
         }
-        // Write tile here to framebuffer for viewer if any
-        {
-            let mut tev_client_mutable = tev_client.lock().unwrap();
-            tev_client_mutable
-                .send(PacketUpdateImage {
-                    image_name: "test",
-                    grab_focus: false,
-                    channel_names: &["R", "G", "B"],
-                    channel_offsets: &[0, 1, 2],
-                    channel_strides: &[3, 3, 3],
-                    x: x_starting_offset as u32,
-                    y: y_starting_offset as u32,
-                    width: 16,
-                    height: 16,
-                    data: &pixel_values_for_viewer,
-                })
-                .unwrap();
-            //info!("Tile id: {:?}", tile_id);
-            //let ten_millis = time::Duration::from_millis(10);
+        radiance
+    }
+}
 
-            //thread::sleep(ten_millis);
-        }
-        //info!("{:?}", curr_tile);
+//MIS weight of a BSDF sample that found a light, delta lobes cannot be matched by light sampling
+fn bsdf_weight(bsdf_sample: &BsdfSample, light_pdf: fp) -> fp {
+    if bsdf_sample.is_specular {
+        return 1.0;
     }
+    power_heuristic(1.0, bsdf_sample.pdf, 1.0, light_pdf)
 }
@@ -1,27 +1,177 @@
 use ndarray::Array2;
-use tev_client::TevClient;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use crate::accel::aabb::Boundable;
 use crate::common::*;
-use crate::film::Film;
-use crate::{SceneCamera, SceneConfig};
-use std::sync::{Arc, Mutex};
+use crate::film::{Film, FilmPixel, FirstHit};
+use crate::materials::Bsdf;
+use crate::preview::PreviewSink;
+use crate::utilities::sampling::power_heuristic;
+use crate::{RenderSettings, SceneCamera, SceneConfig, SceneLights};
+use std::sync::Arc;
 
 pub mod baseintegrator;
 pub mod directlighting;
+pub mod pathtracerbsdf;
+pub mod pathtracernee;
 
 pub trait Integrator {
     #[allow(clippy::too_many_arguments)]
     fn render(
         scene: Arc<SceneConfig>,
-        samples_count: u32,
-        bounces_count: u32,
         camera: Arc<SceneCamera>,
         geometries: Arc<dyn Boundable>,
         //geometries: Arc<SceneGeometries>,
+        lights: Arc<SceneLights>,
         film: Arc<Film>,
-        t_min: fp,
         t_max: fp,
-        tev_client: Arc<Mutex<TevClient>>,
-    ) -> Array2<Spectrum>;
+        preview: Arc<dyn PreviewSink>,
+    ) -> Array2<FilmPixel>;
+}
+
+//Every tile gets its own random number stream, determined by the render seed and the tile index
+//alone so that renders are reproducible regardless of how tiles are scheduled
+pub(crate) fn tile_rng(seed: u64, tile: &TileBounds) -> StdRng {
+    let mut key: [u8; 32] = [0; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&(tile.index as u64).to_le_bytes());
+    StdRng::from_seed(key)
+}
+
+//Radiance of a single camera sample. With consistency checks enabled, samples that are NaN,
+//infinite or negative are reported and left out instead of spoiling the whole pixel.
+pub(crate) fn checked_sample(
+    radiance: Spectrum,
+    x: i32,
+    y: i32,
+    settings: &RenderSettings,
+) -> Spectrum {
+    if settings.enable_consistency_checks
+        && !(radiance.x.is_finite()
+            && radiance.y.is_finite()
+            && radiance.z.is_finite()
+            && radiance.x >= 0.0
+            && radiance.y >= 0.0
+            && radiance.z >= 0.0)
+    {
+        warn!(
+            "Invalid sample {} {} {} at pixel {} {}, discarding it",
+            radiance.x, radiance.y, radiance.z, x, y
+        );
+        return Spectrum::default();
+    }
+    radiance
+}
+
+//Surface a camera ray sees directly, for the albedo, normal and depth AOVs
+pub(crate) fn first_hit(
+    ray: &Ray,
+    geometries: &dyn Boundable,
+    t_min: fp,
+    t_max: fp,
+) -> Option<FirstHit> {
+    let intersection_info =
+        geometries.check_intersection_and_return_closest_hit(ray.clone(), t_min, t_max)?;
+    let mut normal: Vec3 = intersection_info.shading_normal;
+    if intersection_info.normal.dot(ray.d) > 0.0 {
+        normal *= -1.0;
+    }
+    Some(FirstHit {
+        albedo: intersection_info
+            .bsdf
+            .as_ref()
+            .map_or(Spectrum::default(), |bsdf| {
+                bsdf.albedo(intersection_info.uv)
+            }),
+        normal,
+        depth: (intersection_info.point_of_intersection - ray.o).length(),
+    })
+}
+
+//Frame the BSDF is evaluated in, around the shading normal flipped to the side of the geometric
+//normal the integrator shades on
+pub(crate) fn shading_frame_at(intersection_info: &IntersectionInfo, normal: Vec3) -> Frame {
+    let mut shading_normal: Vec3 = intersection_info.shading_normal;
+    if shading_normal.dot(intersection_info.normal) * normal.dot(intersection_info.normal) < 0.0 {
+        shading_normal *= -1.0;
+    }
+    Frame::from_normal_and_tangent(shading_normal, intersection_info.tangent)
+}
+
+//Move a ray origin off the surface, to the side the outgoing direction is on
+pub(crate) fn offset_ray_origin(point: Point3, normal: Vec3, direction: Vec3) -> Point3 {
+    if direction.dot(normal) >= 0.0 {
+        point + normal * EPSILON
+    } else {
+        point - normal * EPSILON
+    }
+}
+
+//Check if nothing blocks the segment from point along direction up to distance
+pub(crate) fn unoccluded(
+    geometries: &dyn Boundable,
+    point: Point3,
+    direction: Vec3,
+    distance: fp,
+    t_min: fp,
+) -> bool {
+    let t_max: fp = distance * (1.0 - EPSILON);
+    let shadow_ray = Ray::new(point, direction, t_min, t_max);
+    geometries
+        .check_intersection_and_return_closest_hit(shadow_ray, t_min, t_max)
+        .is_none()
+}
+
+//Light sampling half of the MIS direct lighting estimate at a surface point: pick one light
+//by power, sample it and weight the contribution against the BSDF sampling strategy
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn sample_one_light(
+    point: Point3,
+    normal: Vec3,
+    shading_frame: &Frame,
+    wo_local: Vec3,
+    bsdf: &dyn Bsdf,
+    uv: Point2,
+    geometries: &dyn Boundable,
+    lights: &SceneLights,
+    t_min: fp,
+    rng: &mut StdRng,
+) -> Spectrum {
+    let (light, light_selection_pdf) = match lights.sample_light(rng.gen()) {
+        Some((light, light_selection_pdf)) if light_selection_pdf > 0.0 => {
+            (light, light_selection_pdf)
+        }
+        _ => return Spectrum::default(),
+    };
+    let light_sample = match light.sample_li(point, Point2::new(rng.gen(), rng.gen())) {
+        Some(light_sample) => light_sample,
+        None => return Spectrum::default(),
+    };
+    if light_sample.pdf <= 0.0 || light_sample.li.is_black() {
+        return Spectrum::default();
+    }
+
+    let wi_local: Vec3 = shading_frame.to_local(light_sample.wi);
+    let f: Spectrum = bsdf.eval(wo_local, wi_local, uv) * wi_local.z.abs();
+    if f.is_black()
+        || !unoccluded(
+            geometries,
+            offset_ray_origin(point, normal, light_sample.wi),
+            light_sample.wi,
+            light_sample.distance,
+            t_min,
+        )
+    {
+        return Spectrum::default();
+    }
+
+    let light_pdf: fp = light_sample.pdf * light_selection_pdf;
+    //BSDF sampling can never find delta lights, nothing to weight against
+    if light.is_delta() {
+        return f * light_sample.li / light_pdf;
+    }
+    let scattering_pdf: fp = bsdf.pdf(wo_local, wi_local, uv);
+    let weight: fp = power_heuristic(1.0, light_pdf, 1.0, scattering_pdf);
+    f * light_sample.li * (weight / light_pdf)
 }
@@ -0,0 +1,100 @@
+use crate::accel::aabb::Boundable;
+use crate::common::*;
+use crate::integrators::{offset_ray_origin, shading_frame_at};
+use crate::materials::Bsdf;
+use crate::{SceneConfig, SceneLights};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+pub struct PathTracerBsdfIntegrator;
+
+impl PathTracerBsdfIntegrator {
+    //Radiance along a camera ray, extending the path only by sampling the BSDF at every vertex.
+    //Light is only picked up when the path happens to hit an emitter or escapes the scene.
+    pub(crate) fn li(
+        camera_ray: Ray,
+        scene: &SceneConfig,
+        geometries: &dyn Boundable,
+        lights: &SceneLights,
+        t_min: fp,
+        t_max: fp,
+        rng: &mut StdRng,
+    ) -> Spectrum {
+        let mut radiance: Spectrum = Spectrum::default();
+        let mut throughput: Spectrum = Spectrum::from(1.0);
+        let mut ray: Ray = camera_ray;
+        let mut bounces: u32 = 0;
+
+        loop {
+            let intersection =
+                geometries.check_intersection_and_return_closest_hit(ray.clone(), t_min, t_max);
+            let intersection_info = match intersection {
+                Some(intersection_info) => intersection_info,
+                None => {
+                    for light in lights.infinite_lights() {
+                        radiance += throughput * light.le(&ray);
+                    }
+                    break;
+                }
+            };
+
+            if let Some(light) = &intersection_info.light {
+                radiance += throughput * light.l(&intersection_info, -ray.d);
+            }
+
+            if bounces >= scene.settings.max_bounces {
+                break;
+            }
+
+            let bsdf: &dyn Bsdf = match intersection_info.bsdf.as_deref() {
+                Some(bsdf) => bsdf,
+                None => break,
+            };
+
+            //Two-sided shading: shade on the side the ray arrived from, one-sided surfaces are black
+            //from behind. Transmissive BSDFs keep the true normal to tell inside from outside.
+            let wo: Vec3 = -ray.d;
+            let mut normal: Vec3 = intersection_info.normal;
+            if !bsdf.has_transmission() {
+                if normal.dot(wo) < 0.0 && !scene.settings.enable_two_sided_shading {
+                    break;
+                }
+                normal.face_outward_normal(wo);
+            }
+            let shading_frame: Frame = shading_frame_at(&intersection_info, normal);
+            let wo_local: Vec3 = shading_frame.to_local(wo);
+
+            let bsdf_sample = match bsdf.sample(
+                wo_local,
+                intersection_info.uv,
+                rng.gen(),
+                Point2::new(rng.gen(), rng.gen()),
+            ) {
+                Some(bsdf_sample) if bsdf_sample.pdf > 0.0 && !bsdf_sample.f.is_black() => {
+                    bsdf_sample
+                }
+                _ => break,
+            };
+            throughput *= bsdf_sample.f * (bsdf_sample.wi.z.abs() / bsdf_sample.pdf);
+            let wi: Vec3 = shading_frame.to_world(bsdf_sample.wi);
+
+            bounces += 1;
+            //Russian roulette once the minimum path length has been reached
+            if bounces > scene.settings.min_bounces {
+                let survival_probability: fp = fp::min(throughput.max_component(), 0.95);
+                if rng.gen::<fp>() >= survival_probability {
+                    break;
+                }
+                throughput /= survival_probability;
+            }
+
+            ray = Ray::new(
+                offset_ray_origin(intersection_info.point_of_intersection, normal, wi),
+                wi,
+                t_min,
+                t_max,
+            );
+        }
+        radiance
+    }
+}
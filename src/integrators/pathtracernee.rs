@@ -0,0 +1,139 @@
+use crate::accel::aabb::Boundable;
+use crate::common::*;
+use crate::integrators::{offset_ray_origin, sample_one_light, shading_frame_at};
+use crate::materials::Bsdf;
+use crate::utilities::sampling::power_heuristic;
+use crate::{SceneConfig, SceneLights};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+pub struct PathTracerNeeIntegrator;
+
+impl PathTracerNeeIntegrator {
+    //Radiance along a camera ray. At every vertex one light is sampled explicitly and the BSDF is
+    //sampled to extend the path; both estimates of direct lighting are combined with the power heuristic.
+    pub(crate) fn li(
+        camera_ray: Ray,
+        scene: &SceneConfig,
+        geometries: &dyn Boundable,
+        lights: &SceneLights,
+        t_min: fp,
+        t_max: fp,
+        rng: &mut StdRng,
+    ) -> Spectrum {
+        let mut radiance: Spectrum = Spectrum::default();
+        let mut throughput: Spectrum = Spectrum::from(1.0);
+        let mut ray: Ray = camera_ray;
+        let mut bounces: u32 = 0;
+        //Solid angle pdf of the BSDF sample that generated the current ray
+        let mut bsdf_pdf: fp = 0.0;
+        //Set when the current ray left a delta lobe, which light sampling could not have found
+        let mut specular_bounce: bool = false;
+
+        let light_sampling: bool =
+            scene.settings.enable_light_sampling && !lights.lights.is_empty();
+
+        loop {
+            let intersection =
+                geometries.check_intersection_and_return_closest_hit(ray.clone(), t_min, t_max);
+            let intersection_info = match intersection {
+                Some(intersection_info) => intersection_info,
+                None => {
+                    for light in lights.infinite_lights() {
+                        //Camera rays, specular bounces and paths without light sampling have no
+                        //competing strategy
+                        let weight: fp = if bounces == 0 || specular_bounce || !light_sampling {
+                            1.0
+                        } else {
+                            let light_pdf: fp =
+                                light.pdf_li(ray.o, ray.d) * lights.selection_pdf(light);
+                            power_heuristic(1.0, bsdf_pdf, 1.0, light_pdf)
+                        };
+                        radiance += throughput * light.le(&ray) * weight;
+                    }
+                    break;
+                }
+            };
+
+            if let Some(light) = &intersection_info.light {
+                let weight: fp = if bounces == 0 || specular_bounce || !light_sampling {
+                    1.0
+                } else {
+                    let light_pdf: fp = light.pdf_li(ray.o, ray.d) * lights.selection_pdf(light);
+                    power_heuristic(1.0, bsdf_pdf, 1.0, light_pdf)
+                };
+                radiance += throughput * light.l(&intersection_info, -ray.d) * weight;
+            }
+
+            if bounces >= scene.settings.max_bounces {
+                break;
+            }
+
+            let bsdf: &dyn Bsdf = match intersection_info.bsdf.as_deref() {
+                Some(bsdf) => bsdf,
+                None => break,
+            };
+
+            //Two-sided shading: shade on the side the ray arrived from, one-sided surfaces are black
+            //from behind. Transmissive BSDFs keep the true normal to tell inside from outside.
+            let wo: Vec3 = -ray.d;
+            let mut normal: Vec3 = intersection_info.normal;
+            if !bsdf.has_transmission() {
+                if normal.dot(wo) < 0.0 && !scene.settings.enable_two_sided_shading {
+                    break;
+                }
+                normal.face_outward_normal(wo);
+            }
+            let shading_frame: Frame = shading_frame_at(&intersection_info, normal);
+            let wo_local: Vec3 = shading_frame.to_local(wo);
+            let point: Point3 = intersection_info.point_of_intersection;
+
+            //Next event estimation with a single light, picked in proportion to its power. The
+            //estimate is divided by the selection probability times the light's own sampling pdf.
+            if light_sampling {
+                radiance += throughput
+                    * sample_one_light(
+                        point,
+                        normal,
+                        &shading_frame,
+                        wo_local,
+                        bsdf,
+                        intersection_info.uv,
+                        geometries,
+                        lights,
+                        t_min,
+                        rng,
+                    );
+            }
+
+            let bsdf_sample = match bsdf.sample(
+                wo_local,
+                intersection_info.uv,
+                rng.gen(),
+                Point2::new(rng.gen(), rng.gen()),
+            ) {
+                Some(bsdf_sample) if bsdf_sample.pdf > 0.0 && !bsdf_sample.f.is_black() => {
+                    bsdf_sample
+                }
+                _ => break,
+            };
+            bsdf_pdf = bsdf_sample.pdf;
+            specular_bounce = bsdf_sample.is_specular;
+            throughput *= bsdf_sample.f * (bsdf_sample.wi.z.abs() / bsdf_sample.pdf);
+            let wi: Vec3 = shading_frame.to_world(bsdf_sample.wi);
+
+            bounces += 1;
+            //Russian roulette once the minimum path length has been reached
+            if bounces > scene.settings.min_bounces {
+                let survival_probability: fp = fp::min(throughput.max_component(), 0.95);
+                if rng.gen::<fp>() >= survival_probability {
+                    break;
+                }
+                throughput /= survival_probability;
+            }
+
+            ray = Ray::new(offset_ray_origin(point, normal, wi), wi, t_min, t_max);
+        }
+        radiance
+    }
+}
@@ -4,32 +4,101 @@ use log::{info, warn};
 use ndarray::Array2;
 use std::error::Error;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 pub mod accel;
 mod camera;
+pub mod cli;
 pub mod common;
 mod film;
 mod geometry;
 pub mod integrators;
+mod lights;
+mod materials;
+pub mod preview;
+pub mod sceneerror;
+mod textures;
 mod utilities;
 
-use crate::accel::aabb::Boundable;
+use crate::accel::aabb::{surrounding_box_primitives, Boundable};
 use crate::camera::pinholecamera::PinholeCamera;
 use crate::camera::Camera;
 use crate::common::*;
-use crate::film::Film;
+use crate::film::tonemapping::Tonemap;
+use crate::film::{Film, FilmPixel};
+use crate::geometry::cube::Cube;
+use crate::geometry::disk::Disk;
+use crate::geometry::quad::Quad;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::transformed::TransformedShape;
 use crate::geometry::triangle::{Triangle, TriangleMesh};
+use crate::geometry::SampleableShape;
 use crate::integrators::baseintegrator::Integrators;
+use crate::lights::arealight::AreaLight;
+use crate::lights::directionallight::DirectionalLight;
+use crate::lights::environmentlight::EnvironmentLight;
+use crate::lights::infinitelight::UniformInfiniteLight;
+use crate::lights::pointlight::PointLight;
+use crate::lights::spotlight::SpotLight;
+use crate::lights::Light;
+use crate::materials::conductor::Conductor;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::fresnel::conductor_preset;
+use crate::materials::lambert::Lambert;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::mtl::MtlMaterial;
+use crate::materials::plastic::Plastic;
+use crate::materials::principled::{PrincipledBsdf, PrincipledParameters};
+use crate::materials::roughconductor::RoughConductor;
+use crate::materials::roughdielectric::RoughDielectric;
+use crate::materials::roughplastic::RoughPlastic;
+use crate::materials::thinsheet::ThinSheet;
+use crate::materials::{Bsdf, DEFAULT_ALBEDO};
+use crate::sceneerror::{SceneError, SceneValue};
+use crate::textures::bitmaptexture::BitmapTexture;
+use crate::textures::checkertexture::CheckerTexture;
+use crate::textures::constanttexture::ConstantTexture;
+use crate::textures::Texture;
+use crate::utilities::imageutils::{exr_compression_from_name, read_hdr_image, ExrSettings};
+use crate::utilities::sampling::Distribution1D;
+use std::collections::HashMap;
+use std::f64::consts::PI;
 use std::sync::Arc;
 use toml::Value;
 
 pub struct SceneConfig {
     pub integrator: Integrators,
+    pub settings: RenderSettings,
+    //Edge length of the square tiles rendered in parallel
+    pub tile_size: usize,
+    //Seeds the random number generators of all tiles
+    pub seed: u64,
+}
+
+//How the integrators sample the scene, from the [renderer] and [integrator] tables
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    pub samples_count: u32,
+    pub max_bounces: u32,
+    //Russian roulette only starts after this many bounces
+    pub min_bounces: u32,
+    //Rays start this far along their direction so they do not hit the surface they leave from
+    pub t_min: fp,
+    pub enable_light_sampling: bool,
+    //Shade surfaces hit from behind as if hit from the front, otherwise back faces are black
+    pub enable_two_sided_shading: bool,
+    //Report and drop samples that are NaN, infinite or negative
+    pub enable_consistency_checks: bool,
 }
 
 pub struct FileNames {
     pub scene_file_name: PathBuf,
-    pub out_file: PathBuf,
+    //Relative output file names are resolved against this, the scene file's directory by default
+    pub output_directory: PathBuf,
+    pub ldr_output_file: Option<PathBuf>,
+    pub hdr_output_file: Option<PathBuf>,
+    pub overwrite_output_files: bool,
+    pub exr_settings: ExrSettings,
 }
 
 #[derive(Default, Clone)]
@@ -41,6 +110,21 @@ pub struct Tile {
 
 pub struct SceneGeometries {
     pub geometries: Vec<Arc<dyn Boundable>>,
+    //Emitters declared as primitives
+    pub lights: Vec<Arc<dyn Light>>,
+}
+
+pub struct SceneMaterials {
+    pub materials: HashMap<String, Arc<dyn Bsdf>>,
+    pub default_material: Arc<dyn Bsdf>,
+}
+
+pub struct SceneLights {
+    pub lights: Vec<Arc<dyn Light>>,
+    //Picks lights in proportion to their power
+    light_selection: Distribution1D,
+    //Index into lights by the address of the light
+    light_indices: HashMap<usize, usize>,
 }
 
 pub struct SceneCamera {
@@ -49,72 +133,63 @@ pub struct SceneCamera {
 
 #[derive(Default)]
 pub struct ImageBuffer {
-    image: Vec<Spectrum>,
+    image: Vec<FilmPixel>,
 }
 
 impl SceneConfig {
-    pub fn parse_args(args: &[String]) -> (PathBuf, toml::Value) {
-        let mut scene_filename = PathBuf::from("");
-        for arg in args {
-            if arg.contains(".toml") {
-                scene_filename = PathBuf::from(Path::new(arg));
-            }
-        }
-
-        //Error cases
-        if scene_filename.to_str() == Some("") {
-            std::panic::panic_any(
-                "No scene configuration file specified or something else went wrong!".to_string(),
-            );
-        }
-
-        //Parse scene
-        let scene_file_contents = fs::read_to_string(scene_filename.clone()).unwrap();
-
-        let parsed_scene_result = scene_file_contents.parse::<Value>();
+    pub fn load_scene_file(scene_filename: &Path) -> Result<toml::Value, SceneError> {
+        let scene_file_contents =
+            fs::read_to_string(scene_filename).map_err(|source| SceneError::Io {
+                file: scene_filename.to_path_buf(),
+                source,
+            })?;
 
         //info!(&parsed_scene_toml);
-        match parsed_scene_result {
-            Ok(parsed_scene_toml) => (scene_filename, parsed_scene_toml),
-            Err(e) => {
-                panic!("Failed to parse scene file with error: {:?}", e);
-            }
-        }
+        scene_file_contents
+            .parse::<Value>()
+            .map_err(|source| SceneError::Toml {
+                file: scene_filename.to_path_buf(),
+                source,
+            })
     }
 
-    pub fn construct_film(parsed_scene_toml: toml::Value) -> Film {
+    pub fn construct_film(
+        scene_filename: &Path,
+        parsed_scene_toml: toml::Value,
+    ) -> Result<Film, SceneError> {
+        let scene = SceneValue::root(scene_filename, &parsed_scene_toml);
         //Film
-        let width = parsed_scene_toml["camera"]["resolution"][0]
-            .as_float()
-            .unwrap() as i32;
-        let height = parsed_scene_toml["camera"]["resolution"][1]
-            .as_float()
-            .unwrap() as i32;
-        let fov_degrees = parsed_scene_toml["camera"]["fov"].as_float().unwrap();
+        let resolution = scene.get("camera").get("resolution");
+        let width = resolution.at(0).number()? as i32;
+        let height = resolution.at(1).number()? as i32;
+        if width < 1 || height < 1 {
+            return Err(resolution.invalid("a positive width and height"));
+        }
+        let fov_degrees = scene.get("camera").get("fov").number()?;
         let mut film = Film::default();
         film.new_film(width, height, fov_degrees);
-        film
+        //Tonemapping for LDR output
+        let tonemap = scene.get("camera").get("tonemap");
+        if let Some(tonemap_name) = tonemap.optional_string()? {
+            film.tonemap = Tonemap::from_name(tonemap_name)
+                .ok_or_else(|| tonemap.invalid("linear, gamma, reinhard, filmic or aces"))?;
+        }
+        film.exposure = scene.get("camera").get("exposure").number_or(0.0)?;
+        Ok(film)
     }
 
     pub fn construct_scene(
         scene_filename: PathBuf,
         parsed_scene_toml: toml::Value,
-    ) -> Result<(SceneConfig, FileNames), Box<dyn Error>> {
-        //Material
+    ) -> Result<(SceneConfig, FileNames), SceneError> {
+        let scene = SceneValue::root(&scene_filename, &parsed_scene_toml);
 
         //Integrator
-        let integrator_string = &parsed_scene_toml["integrator"]["type"]
-            .as_str()
-            .unwrap()
-            .to_ascii_lowercase();
-        let type_of_integrator: Integrators = match integrator_string.as_ref() {
-            "direct_lighting" => Integrators::DirectLighting,
-
-            "path_tracer_bsdf" => Integrators::PathTracerBsdf,
-
-            "path_tracer_nee" => Integrators::PathTracerNee,
-
-            _ => {
+        let integrator = scene.get("integrator");
+        let integrator_string = integrator.get("type").string()?.to_ascii_lowercase();
+        let type_of_integrator: Integrators = match Integrators::from_name(&integrator_string) {
+            Some(integrator) => integrator,
+            None => {
                 warn!(
                     "Warning: Found unsupported integrator {}, falling back to DirectLighting...",
                     integrator_string
@@ -123,69 +198,121 @@ impl SceneConfig {
             }
         };
 
-        //Output pfm
-        let output_file_name = &parsed_scene_toml["renderer"]["hdr_output_file"]
-            .as_str()
-            .unwrap()
-            .to_string();
-        let output_file_full_path = "sandbox/".to_string() + output_file_name;
-        info!("{}", &output_file_full_path);
-        let out_file = PathBuf::from(output_file_full_path);
+        let settings = RenderSettings::construct(&scene)?;
+
+        let tile_size = scene
+            .get("renderer")
+            .get("tile_size")
+            .number_or(TILE_SIZE as fp)?;
+        if tile_size < 1.0 {
+            return Err(scene
+                .get("renderer")
+                .get("tile_size")
+                .invalid("a positive number"));
+        }
+
+        //Outputs
+        let renderer = scene.get("renderer");
+        let ldr_output_file = renderer.get("output_file").optional_string()?;
+        let hdr_output_file = renderer.get("hdr_output_file").optional_string()?;
+        if ldr_output_file.is_none() && hdr_output_file.is_none() {
+            return Err(renderer.get("hdr_output_file").missing());
+        }
+        let overwrite_output_files = renderer.get("overwrite_output_files").boolean_or(true)?;
+        let mut exr_settings = ExrSettings::default();
+        let exr_compression = renderer.get("exr_compression");
+        if let Some(compression_name) = exr_compression.optional_string()? {
+            exr_settings.compression = exr_compression_from_name(compression_name)
+                .ok_or_else(|| exr_compression.invalid("none, rle, zips, zip or piz"))?;
+        }
+        exr_settings.half_float = renderer.get("exr_half_float").boolean_or(false)?;
+        let output_directory = scene_filename
+            .parent()
+            .map_or(PathBuf::new(), Path::to_path_buf);
 
         Ok((
             SceneConfig {
                 integrator: type_of_integrator,
+                settings,
+                tile_size: tile_size as usize,
+                seed: 0,
             },
             FileNames {
-                scene_file_name: scene_filename,
-                out_file,
+                scene_file_name: scene_filename.clone(),
+                output_directory,
+                ldr_output_file: ldr_output_file.map(PathBuf::from),
+                hdr_output_file: hdr_output_file.map(PathBuf::from),
+                overwrite_output_files,
+                exr_settings,
             },
         ))
     }
 }
 
-impl SceneCamera {
-    pub fn construct_camera(parsed_scene_toml: toml::Value) -> SceneCamera {
-        //Camera
-        let camera_position: Point3 = Point3 {
-            x: parsed_scene_toml["camera"]["transform"]["position"][0]
-                .as_float()
-                .unwrap(),
-            y: parsed_scene_toml["camera"]["transform"]["position"][1]
-                .as_float()
-                .unwrap(),
-            z: parsed_scene_toml["camera"]["transform"]["position"][2]
-                .as_float()
-                .unwrap(),
+impl RenderSettings {
+    //Defaults follow Tungsten
+    fn construct(scene: &SceneValue<'_>) -> Result<RenderSettings, SceneError> {
+        let renderer = scene.get("renderer");
+        let integrator = scene.get("integrator");
+        let count = |value: SceneValue<'_>, default: fp, minimum: fp| -> Result<u32, SceneError> {
+            let count = value.number_or(default)?;
+            if count < minimum || count.fract() != 0.0 {
+                return Err(value.invalid(if minimum > 0.0 {
+                    "a positive whole number"
+                } else {
+                    "a whole number, zero or more"
+                }));
+            }
+            Ok(count as u32)
         };
 
-        let camera_look_at: Point3 = Point3 {
-            x: parsed_scene_toml["camera"]["transform"]["look_at"][0]
-                .as_float()
-                .unwrap(),
-            y: parsed_scene_toml["camera"]["transform"]["look_at"][1]
-                .as_float()
-                .unwrap(),
-            z: parsed_scene_toml["camera"]["transform"]["look_at"][2]
-                .as_float()
-                .unwrap(),
-        };
+        let samples_count = count(renderer.get("spp"), 64.0, 1.0)?;
+        let max_bounces = count(integrator.get("max_bounces"), 64.0, 0.0)?;
+        let min_bounces = count(integrator.get("min_bounces"), 0.0, 0.0)?;
+        if min_bounces > max_bounces {
+            warn!(
+                "Warning: min_bounces {} is above max_bounces {}, paths will never be terminated by russian roulette",
+                min_bounces, max_bounces
+            );
+        }
+        let t_min = integrator.get("ray_epsilon").number_or(1e-5)?;
+        if t_min.is_nan() || t_min < 0.0 {
+            return Err(integrator
+                .get("ray_epsilon")
+                .invalid("a small non-negative number"));
+        }
 
-        let camera_up: Point3 = Point3 {
-            x: parsed_scene_toml["camera"]["transform"]["up"][0]
-                .as_float()
-                .unwrap(),
-            y: parsed_scene_toml["camera"]["transform"]["up"][1]
-                .as_float()
-                .unwrap(),
-            z: parsed_scene_toml["camera"]["transform"]["up"][2]
-                .as_float()
-                .unwrap(),
-        };
+        Ok(RenderSettings {
+            samples_count,
+            max_bounces,
+            min_bounces,
+            t_min,
+            enable_light_sampling: integrator.get("enable_light_sampling").boolean_or(true)?,
+            enable_two_sided_shading: integrator
+                .get("enable_two_sided_shading")
+                .boolean_or(true)?,
+            enable_consistency_checks: integrator
+                .get("enable_consistency_checks")
+                .boolean_or(false)?,
+        })
+    }
+}
 
-        let type_of_camera = &parsed_scene_toml["camera"]["type"].as_str().unwrap();
+impl SceneCamera {
+    pub fn construct_camera(
+        scene_filename: &Path,
+        parsed_scene_toml: toml::Value,
+    ) -> Result<SceneCamera, SceneError> {
+        let scene = SceneValue::root(scene_filename, &parsed_scene_toml);
+        //Camera
+        let camera_transform = scene.get("camera").get("transform");
+        let camera_position: Point3 = camera_transform.get("position").vector3()?;
+        let camera_look_at: Point3 = camera_transform.get("look_at").vector3()?;
+        let camera_up: Point3 = camera_transform.get("up").vector3()?;
 
-        let camera: Box<dyn Camera + Send + Sync> = match *type_of_camera {
+        let type_of_camera = scene.get("camera").get("type").string()?;
+
+        let camera: Box<dyn Camera + Send + Sync> = match type_of_camera {
             "pinhole" => Box::new(PinholeCamera::new(
                 camera_position,
                 camera_look_at,
@@ -200,12 +327,12 @@ impl SceneCamera {
                 ))
             }
         };
-        SceneCamera { camera }
+        Ok(SceneCamera { camera })
     }
 
-    pub fn generate_camera_ray(&self, x: i32, y: i32, film: &Film) -> Ray {
+    pub fn generate_camera_ray(&self, x: i32, y: i32, pixel_sample: Point2, film: &Film) -> Ray {
         self.camera
-            .generate_camera_ray(x, film.height - y - 1, film)
+            .generate_camera_ray(x, film.height - y - 1, pixel_sample, film)
     }
 }
 
@@ -240,59 +367,551 @@ impl SceneGeometries {
     pub fn construct_geometries(
         scene_filename: PathBuf,
         parsed_scene_toml: toml::Value,
-    ) -> SceneGeometries {
+        scene_materials: &SceneMaterials,
+    ) -> Result<SceneGeometries, SceneError> {
+        let scene = SceneValue::root(&scene_filename, &parsed_scene_toml);
         //Geometry
         let mut geometries: Vec<Arc<dyn Boundable>> = vec![];
+        let mut lights: Vec<Arc<dyn Light>> = vec![];
         let mut num_triangles: usize = 0;
-        for i in &parsed_scene_toml["primitives"].as_array() {
-            for j in *i {
-                let type_of_geometry = j["type"].as_str().unwrap();
-                //Triangle mesh
-                match type_of_geometry {
-                    "mesh" => {
-                        //Process the file path to ensure the meshes are found
-                        let mut current_directory = PathBuf::from(scene_filename.parent().unwrap());
-                        let mesh_location_and_name = j["file"].as_str().unwrap();
-                        current_directory.push(mesh_location_and_name);
-                        let mesh_absolute_path = current_directory.canonicalize().unwrap();
-                        //info!(mesh_absolute_path);
-                        let input_meshes = TriangleMesh::new(mesh_absolute_path);
-                        for input_mesh in input_meshes {
-                            let triangles: Vec<Triangle> = input_mesh.get_triangles_from_mesh();
-                            for triangle in triangles {
-                                geometries.push(Arc::new(triangle));
+        for j in scene.get("primitives").entries()? {
+            let type_of_geometry = j.get("type").string()?;
+            let bsdf = scene_materials.get_material(j.get("bsdf").optional_string()?);
+            let emission = match j.get("emission") {
+                emission if emission.is_present() => Some(parse_texture(&emission, 1.0)?),
+                _ => None,
+            };
+            let transform = parse_transform(&j.get("transform"))?;
+            //Triangle mesh
+            match type_of_geometry {
+                "mesh" => {
+                    //Process the file path to ensure the meshes are found
+                    let mut current_directory =
+                        PathBuf::from(scene_filename.parent().unwrap_or_else(|| Path::new("")));
+                    let mesh_location_and_name = j.get("file").string()?;
+                    current_directory.push(mesh_location_and_name);
+                    let mesh_absolute_path =
+                        current_directory
+                            .canonicalize()
+                            .map_err(|source| SceneError::Io {
+                                file: current_directory.clone(),
+                                source,
+                            })?;
+                    //info!(mesh_absolute_path);
+                    let (input_meshes, input_materials) =
+                        TriangleMesh::new(mesh_absolute_path.clone()).map_err(|source| {
+                            SceneError::Mesh {
+                                file: mesh_absolute_path.clone(),
+                                source,
                             }
+                        })?;
+                    //The .mtl materials are only used when the entry names no bsdf, its own
+                    //emission still takes precedence over Ke
+                    let mesh_directory =
+                        mesh_absolute_path.parent().unwrap_or_else(|| Path::new(""));
+                    let mtl_materials: Vec<MtlMaterial> = if j.get("bsdf").is_present() {
+                        vec![]
+                    } else {
+                        input_materials
+                            .iter()
+                            .map(|material| MtlMaterial::new(material, mesh_directory))
+                            .collect()
+                    };
+                    for mut input_mesh in input_meshes {
+                        input_mesh.apply_transform(&transform);
+                        let (mesh_bsdf, mesh_emission, bump_map) = match input_mesh
+                            .material_id
+                            .and_then(|material_id| mtl_materials.get(material_id))
+                        {
+                            Some(material) => (
+                                material.bsdf.clone(),
+                                emission.clone().or_else(|| material.emission.clone()),
+                                material.bump_map.clone(),
+                            ),
+                            None => (bsdf.clone(), emission.clone(), None),
+                        };
+                        let triangles: Vec<Triangle> =
+                            input_mesh.get_triangles_from_mesh(mesh_bsdf);
+                        for mut triangle in triangles {
+                            triangle.bump_map = bump_map.clone();
+                            //Every triangle of an emissive mesh is a light of its own
+                            triangle.light = make_area_light(&triangle, &mesh_emission);
+                            lights.extend(triangle.light.clone());
+                            geometries.push(Arc::new(triangle));
                         }
-                        num_triangles += geometries.len()
                     }
-                    _ => {
-                        warn!(
-                            "Warning: found unsupported geometry type {}, skipping...",
-                            type_of_geometry
-                        );
+                    num_triangles += geometries.len()
+                }
+                "sphere" => {
+                    let center = j.get("center").vector3_or(Point3::from(0.0))?;
+                    let radius = j.get("radius").number_or(1.0)?;
+                    let mut sphere =
+                        TransformedShape::new(Sphere::new(center, radius, bsdf.clone()), transform);
+                    sphere.shape.light = make_area_light(&sphere, &emission);
+                    lights.extend(sphere.shape.light.clone());
+                    geometries.push(Arc::new(sphere));
+                }
+                "quad" => {
+                    let mut quad = TransformedShape::new(Quad::new(bsdf.clone()), transform);
+                    quad.shape.light = make_area_light(&quad, &emission);
+                    lights.extend(quad.shape.light.clone());
+                    geometries.push(Arc::new(quad));
+                }
+                "disk" => {
+                    let mut disk = TransformedShape::new(Disk::new(bsdf.clone()), transform);
+                    disk.shape.light = make_area_light(&disk, &emission);
+                    lights.extend(disk.shape.light.clone());
+                    geometries.push(Arc::new(disk));
+                }
+                "cube" => {
+                    let mut cube = TransformedShape::new(Cube::new(bsdf.clone()), transform);
+                    cube.shape.light = make_area_light(&cube, &emission);
+                    lights.extend(cube.shape.light.clone());
+                    geometries.push(Arc::new(cube));
+                }
+                "infinite_sphere" => {
+                    let emission = j.get("emission");
+                    match emission.value() {
+                        Some(Value::String(_)) => {
+                            let file = emission.file_path()?;
+                            let image = read_hdr_image(&file)
+                                .map_err(|source| SceneError::Io { file, source })?;
+                            //Only the rotation is meaningful for a light at infinity
+                            let rotation = j
+                                .get("transform")
+                                .get("rotation")
+                                .vector3_or(Vector3::from(0.0))?;
+                            let light_to_world = Transform::from_position_rotation_scale(
+                                Vector3::from(0.0),
+                                rotation,
+                                Vector3::from(1.0),
+                            );
+                            let sample = j.get("sample").boolean_or(true)?;
+                            lights.push(Arc::new(EnvironmentLight::new(
+                                Arc::new(image),
+                                light_to_world,
+                                sample,
+                            )));
+                        }
+                        _ => {
+                            let radiance = parse_scalar_or_vector3(&emission, 1.0)?;
+                            lights.push(Arc::new(UniformInfiniteLight::new(radiance)));
+                        }
                     }
                 }
+                _ => {
+                    warn!(
+                        "Warning: found unsupported geometry type {}, skipping...",
+                        type_of_geometry
+                    );
+                }
             }
         }
+        if geometries.is_empty() {
+            return Err(scene.get("primitives").invalid("at least one shape"));
+        }
         warn!("Total no. of triangles: {}", num_triangles);
-        SceneGeometries { geometries }
+        Ok(SceneGeometries { geometries, lights })
+    }
+}
+
+//Emissive analytic shapes are lights as well as geometry, the light keeps its own copy of the shape
+fn make_area_light<T: SampleableShape + Clone + 'static>(
+    shape: &T,
+    emission: &Option<Arc<dyn Texture>>,
+) -> Option<Arc<dyn Light>> {
+    emission.as_ref().map(|emission| {
+        Arc::new(AreaLight::new(Arc::new(shape.clone()), emission.clone())) as Arc<dyn Light>
+    })
+}
+
+impl SceneMaterials {
+    pub fn construct_materials(
+        scene_filename: &Path,
+        parsed_scene_toml: toml::Value,
+    ) -> Result<SceneMaterials, SceneError> {
+        let scene = SceneValue::root(scene_filename, &parsed_scene_toml);
+        let default_material: Arc<dyn Bsdf> = Arc::new(Lambert::new(Arc::new(
+            ConstantTexture::new(Spectrum::from(DEFAULT_ALBEDO)),
+        )));
+        let mut materials: HashMap<String, Arc<dyn Bsdf>> = HashMap::new();
+        for bsdf in scene.get("bsdfs").entries()? {
+            let name = bsdf.get("name").string()?.to_string();
+            let type_of_bsdf = bsdf.get("type").string()?;
+            let albedo = |default_value: fp| parse_texture(&bsdf.get("albedo"), default_value);
+            let material: Arc<dyn Bsdf> = match type_of_bsdf {
+                "lambert" => Arc::new(Lambert::new(albedo(DEFAULT_ALBEDO)?)),
+                "conductor" => {
+                    let (eta, k) = parse_complex_ior(&bsdf)?;
+                    Arc::new(Conductor::new(albedo(1.0)?, eta, k))
+                }
+                "rough_conductor" => {
+                    let (eta, k) = parse_complex_ior(&bsdf)?;
+                    let distribution = parse_microfacet_distribution(&bsdf)?;
+                    Arc::new(RoughConductor::new(albedo(1.0)?, eta, k, distribution))
+                }
+                "dielectric" => {
+                    let ior = parse_ior(&bsdf)?;
+                    Arc::new(Dielectric::new(albedo(1.0)?, ior))
+                }
+                "rough_dielectric" => {
+                    let ior = parse_ior(&bsdf)?;
+                    let distribution = parse_microfacet_distribution(&bsdf)?;
+                    Arc::new(RoughDielectric::new(albedo(1.0)?, ior, distribution))
+                }
+                "thinsheet" => Arc::new(ThinSheet::new(
+                    albedo(1.0)?,
+                    parse_ior(&bsdf)?,
+                    bsdf.get("thickness").number_or(0.5)?,
+                    parse_scalar_or_vector3(&bsdf.get("sigma_a"), 0.0)?,
+                    bsdf.get("enable_interference").boolean_or(false)?,
+                )),
+                "plastic" => Arc::new(Plastic::new(
+                    albedo(DEFAULT_ALBEDO)?,
+                    parse_ior(&bsdf)?,
+                    bsdf.get("thickness").number_or(1.0)?,
+                    parse_scalar_or_vector3(&bsdf.get("sigma_a"), 0.0)?,
+                )),
+                "rough_plastic" => Arc::new(RoughPlastic::new(
+                    albedo(DEFAULT_ALBEDO)?,
+                    parse_ior(&bsdf)?,
+                    bsdf.get("thickness").number_or(1.0)?,
+                    parse_scalar_or_vector3(&bsdf.get("sigma_a"), 0.0)?,
+                    parse_microfacet_distribution(&bsdf)?,
+                )),
+                "principled" => {
+                    //Parameters outside of [0, 1] are clamped into it
+                    let parameter = |key: &str, default_value: fp| {
+                        bsdf.get(key)
+                            .number_or(default_value)
+                            .map(|value| value.clamp(0.0, 1.0))
+                    };
+                    Arc::new(PrincipledBsdf::new(PrincipledParameters {
+                        base_color: parse_texture(&bsdf.get("base_color"), DEFAULT_ALBEDO)?,
+                        metallic: parameter("metallic", 0.0)?,
+                        roughness: parameter("roughness", 0.5)?,
+                        specular: parameter("specular", 0.5)?,
+                        specular_tint: parameter("specular_tint", 0.0)?,
+                        sheen: parameter("sheen", 0.0)?,
+                        sheen_tint: parameter("sheen_tint", 0.5)?,
+                        clearcoat: parameter("clearcoat", 0.0)?,
+                        clearcoat_gloss: parameter("clearcoat_gloss", 1.0)?,
+                        transmission: parameter("transmission", 0.0)?,
+                        anisotropic: parameter("anisotropic", 0.0)?,
+                        ior: parse_ior(&bsdf)?,
+                    }))
+                }
+                _ => {
+                    warn!(
+                        "Warning: found unsupported bsdf type {} for {}, falling back to lambert...",
+                        type_of_bsdf, name
+                    );
+                    Arc::new(Lambert::new(albedo(DEFAULT_ALBEDO)?))
+                }
+            };
+            if materials.insert(name.clone(), material).is_some() {
+                warn!(
+                    "Warning: bsdf {} is defined more than once, using the last one",
+                    name
+                );
+            }
+        }
+        warn!("Total no. of materials: {}", materials.len());
+        Ok(SceneMaterials {
+            materials,
+            default_material,
+        })
+    }
+
+    //Look up a material by the name a primitive refers to it with
+    pub fn get_material(&self, name: Option<&str>) -> Arc<dyn Bsdf> {
+        match name {
+            Some(name) => match self.materials.get(name) {
+                Some(material) => material.clone(),
+                None => {
+                    warn!(
+                        "Warning: bsdf {} not found, falling back to the default material...",
+                        name
+                    );
+                    self.default_material.clone()
+                }
+            },
+            None => self.default_material.clone(),
+        }
+    }
+}
+
+//Conductors take the name of a metal preset in "material", copper by default, and "eta" or "k"
+//override the preset's values
+fn parse_complex_ior(bsdf: &SceneValue<'_>) -> Result<(Spectrum, Spectrum), SceneError> {
+    let material = bsdf.get("material").optional_string()?.unwrap_or("Cu");
+    let (eta, k) = conductor_preset(material).unwrap_or_else(|| {
+        warn!(
+            "Warning: found unknown conductor material {}, falling back to Cu...",
+            material
+        );
+        conductor_preset("Cu").expect("copper preset")
+    });
+    let eta = match bsdf.get("eta") {
+        value if value.is_present() => parse_scalar_or_vector3(&value, 1.0)?,
+        _ => eta,
+    };
+    let k = match bsdf.get("k") {
+        value if value.is_present() => parse_scalar_or_vector3(&value, 0.0)?,
+        _ => k,
+    };
+    Ok((eta, k))
+}
+
+//IOR of the inside of a dielectric relative to the outside, glass by default
+fn parse_ior(bsdf: &SceneValue<'_>) -> Result<fp, SceneError> {
+    let ior = bsdf.get("ior").number_or(1.5)?;
+    if ior <= 0.0 {
+        return Err(bsdf.get("ior").invalid("a positive number"));
+    }
+    Ok(ior)
+}
+
+//Microfacet distribution of a rough bsdf, GGX with a roughness of 0.1 by default
+fn parse_microfacet_distribution(
+    bsdf: &SceneValue<'_>,
+) -> Result<MicrofacetDistribution, SceneError> {
+    let roughness = bsdf.get("roughness").number_or(0.1)?;
+    let name = bsdf.get("distribution").optional_string()?.unwrap_or("ggx");
+    match MicrofacetDistribution::from_name(name, roughness) {
+        Some(distribution) => Ok(distribution),
+        None => {
+            warn!(
+                "Warning: found unsupported microfacet distribution {}, falling back to ggx...",
+                name
+            );
+            let alpha = roughness.max(1e-4);
+            Ok(MicrofacetDistribution::Ggx {
+                alpha_x: alpha,
+                alpha_y: alpha,
+            })
+        }
+    }
+}
+
+//Missing entries of a [primitives.transform] table leave that part of the transform as identity,
+//scale may also be a single uniform factor
+fn parse_transform(value: &SceneValue<'_>) -> Result<Transform, SceneError> {
+    Ok(Transform::from_position_rotation_scale(
+        value.get("position").vector3_or(Vector3::from(0.0))?,
+        value.get("rotation").vector3_or(Vector3::from(0.0))?,
+        parse_scalar_or_vector3(&value.get("scale"), 1.0)?,
+    ))
+}
+
+//Colors and scales are either a single value for all three components or a triplet
+fn parse_scalar_or_vector3(
+    value: &SceneValue<'_>,
+    default_value: fp,
+) -> Result<Vector3, SceneError> {
+    match value.value() {
+        None => Ok(Vector3::from(default_value)),
+        Some(Value::Float(_)) | Some(Value::Integer(_)) => Ok(Vector3::from(value.number()?)),
+        Some(Value::Array(_)) => value.vector3(),
+        Some(_) => Err(value.invalid("a number or an array of 3 numbers")),
+    }
+}
+
+//Textures are given either as a constant color or as a table describing a procedural texture
+fn parse_texture(
+    value: &SceneValue<'_>,
+    default_value: fp,
+) -> Result<Arc<dyn Texture>, SceneError> {
+    match value.value() {
+        Some(Value::Table(_)) => {}
+        Some(Value::String(_)) => {
+            let file = value.file_path()?;
+            return match read_hdr_image(&file) {
+                Ok(image) => Ok(Arc::new(BitmapTexture::new(Arc::new(image)))),
+                Err(source) if source.kind() == io::ErrorKind::Unsupported => {
+                    warn!(
+                        "Warning: found unsupported texture {}, falling back to a constant...",
+                        file.display()
+                    );
+                    Ok(Arc::new(ConstantTexture::new(Spectrum::from(
+                        default_value,
+                    ))))
+                }
+                Err(source) => Err(SceneError::Io { file, source }),
+            };
+        }
+        _ => {
+            return Ok(Arc::new(ConstantTexture::new(parse_scalar_or_vector3(
+                value,
+                default_value,
+            )?)))
+        }
+    }
+    let type_of_texture = value.get("type").string()?;
+    match type_of_texture {
+        "checker" => Ok(Arc::new(CheckerTexture::new(
+            parse_scalar_or_vector3(&value.get("on_color"), 0.8)?,
+            parse_scalar_or_vector3(&value.get("off_color"), 0.2)?,
+            value.get("res_u").number_or(20.0)?,
+            value.get("res_v").number_or(20.0)?,
+        ))),
+        _ => {
+            warn!(
+                "Warning: found unsupported texture type {}, falling back to a constant...",
+                type_of_texture
+            );
+            Ok(Arc::new(ConstantTexture::new(Spectrum::from(
+                default_value,
+            ))))
+        }
+    }
+}
+
+impl SceneLights {
+    pub fn construct_lights(
+        scene_filename: &Path,
+        parsed_scene_toml: toml::Value,
+        scene_geometries: &SceneGeometries,
+    ) -> Result<SceneLights, SceneError> {
+        let scene = SceneValue::root(scene_filename, &parsed_scene_toml);
+        let mut lights: Vec<Arc<dyn Light>> = scene_geometries.lights.clone();
+        //Lights without a shape, declared in their own [[lights]] tables
+        for j in scene.get("lights").entries()? {
+            let type_of_light = j.get("type").string()?;
+            match type_of_light {
+                "point" => {
+                    let position = j.get("position").vector3_or(Point3::from(0.0))?;
+                    let intensity = match j.get("power") {
+                        power if power.is_present() => {
+                            parse_scalar_or_vector3(&power, 1.0)? / (4.0 * PI)
+                        }
+                        _ => parse_scalar_or_vector3(&j.get("intensity"), 1.0)?,
+                    };
+                    lights.push(Arc::new(PointLight::new(position, intensity)));
+                }
+                "spot" => {
+                    let position = j.get("position").vector3_or(Point3::from(0.0))?;
+                    let direction = j.get("direction").vector3_or(Vec3::new(0.0, -1.0, 0.0))?;
+                    let cone_angle = j.get("cone_angle").number_or(30.0)?;
+                    let falloff_start = j.get("falloff_start").number_or(cone_angle)?;
+                    let spot_light = |intensity: Spectrum| {
+                        SpotLight::new(position, direction, intensity, cone_angle, falloff_start)
+                    };
+                    let intensity = match j.get("power") {
+                        //Power is spread over the cone, scale a unit intensity light to match it
+                        power if power.is_present() => {
+                            parse_scalar_or_vector3(&power, 1.0)?
+                                / spot_light(Spectrum::from(1.0)).power(0.0)
+                        }
+                        _ => parse_scalar_or_vector3(&j.get("intensity"), 1.0)?,
+                    };
+                    lights.push(Arc::new(spot_light(intensity)));
+                }
+                "directional" => {
+                    let direction = j.get("direction").vector3_or(Vec3::new(0.0, -1.0, 0.0))?;
+                    let irradiance = parse_scalar_or_vector3(&j.get("irradiance"), 1.0)?;
+                    lights.push(Arc::new(DirectionalLight::new(direction, irradiance)));
+                }
+                _ => {
+                    warn!(
+                        "Warning: found unsupported light type {}, skipping...",
+                        type_of_light
+                    );
+                }
+            }
+        }
+        if lights.is_empty() {
+            warn!("Warning: scene has no emitters, it will render black...");
+        }
+        warn!("Total no. of lights: {}", lights.len());
+
+        let scene_bounds = surrounding_box_primitives(scene_geometries.geometries.clone());
+        let scene_radius: fp = 0.5 * (scene_bounds.max - scene_bounds.min).length();
+        let light_selection = Distribution1D::new(
+            lights
+                .iter()
+                .map(|light| light.power(scene_radius).luminance().max(0.0))
+                .collect(),
+        );
+        let light_indices: HashMap<usize, usize> = lights
+            .iter()
+            .enumerate()
+            .map(|(index, light)| (light_address(light), index))
+            .collect();
+        Ok(SceneLights {
+            lights,
+            light_selection,
+            light_indices,
+        })
     }
+
+    //A light picked in proportion to its power, along with the probability of picking it
+    pub fn sample_light(&self, u: fp) -> Option<(&Arc<dyn Light>, fp)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let (index, pdf) = self.light_selection.sample_discrete(u);
+        Some((&self.lights[index], pdf))
+    }
+
+    //Probability of sample_light picking the given light
+    pub fn selection_pdf(&self, light: &Arc<dyn Light>) -> fp {
+        match self.light_indices.get(&light_address(light)) {
+            Some(index) => self.light_selection.discrete_pdf(*index),
+            None => 0.0,
+        }
+    }
+
+    pub fn infinite_lights(&self) -> impl Iterator<Item = &Arc<dyn Light>> {
+        self.lights.iter().filter(|light| light.is_infinite())
+    }
+}
+
+fn light_address(light: &Arc<dyn Light>) -> usize {
+    Arc::as_ptr(light) as *const () as usize
 }
 
 impl ImageBuffer {
     pub fn new(size: usize) -> ImageBuffer {
         ImageBuffer {
-            image: vec![Vector3::from(0.1); size],
+            image: vec![
+                FilmPixel {
+                    radiance: Vector3::from(0.1),
+                    ..Default::default()
+                };
+                size
+            ],
         }
     }
 
-    pub fn write_tile(&mut self, image: Array2<Spectrum>) {
+    pub fn write_tile(&mut self, image: Array2<FilmPixel>) {
         self.image = image.into_raw_vec();
     }
 }
 
+impl FileNames {
+    //Where an output file ends up. Existing files are kept when overwriting is off, the new file
+    //then gets the first free numbered name, e.g. render_1.pfm next to render.pfm.
+    pub fn output_path(&self, file: &Path) -> PathBuf {
+        let path = self.output_directory.join(file);
+        if self.overwrite_output_files || !path.exists() {
+            return path;
+        }
+        let stem = path
+            .file_stem()
+            .map_or("render".into(), |stem| stem.to_string_lossy());
+        let extension = path.extension().map_or(String::new(), |extension| {
+            format!(".{}", extension.to_string_lossy())
+        });
+        (1..)
+            .map(|index| path.with_file_name(format!("{}_{}{}", stem, index, extension)))
+            .find(|candidate| !candidate.exists())
+            .unwrap()
+    }
+}
+
 pub fn write_output(
-    out_file: PathBuf,
+    file_names: &FileNames,
     film: Film,
     image_buffer: ImageBuffer,
 ) -> Result<(), Box<dyn Error>> {
@@ -300,5 +919,91 @@ pub fn write_output(
     let width = borrowed_film.width;
     let height = borrowed_film.height;
 
-    utilities::imageutils::write_pfm(out_file, image_buffer.image, width, height)
+    if let Some(hdr_output_file) = &file_names.hdr_output_file {
+        let out_file = file_names.output_path(hdr_output_file);
+        match out_file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("pfm") => {
+                info!("Writing {}", out_file.display());
+                let radiance: Vec<Spectrum> = image_buffer
+                    .image
+                    .iter()
+                    .map(|pixel| pixel.radiance)
+                    .collect();
+                utilities::imageutils::write_pfm(out_file, radiance, width, height)?;
+            }
+            Some("exr") => {
+                info!("Writing {}", out_file.display());
+                utilities::imageutils::write_exr(
+                    out_file,
+                    &image_buffer.image,
+                    width,
+                    height,
+                    file_names.exr_settings,
+                )?;
+            }
+            _ => warn!(
+                "Warning: unsupported HDR output format {}, skipping it...",
+                out_file.display()
+            ),
+        }
+    }
+    if let Some(ldr_output_file) = &file_names.ldr_output_file {
+        let out_file = file_names.output_path(ldr_output_file);
+        match out_file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("png") => {
+                info!("Writing {}", out_file.display());
+                let display_pixels: Vec<Spectrum> = image_buffer
+                    .image
+                    .iter()
+                    .map(|pixel| borrowed_film.tonemap_pixel(pixel.radiance))
+                    .collect();
+                utilities::imageutils::write_png(out_file, display_pixels, width, height)?;
+            }
+            _ => warn!(
+                "Warning: unsupported LDR output format {}, skipping it...",
+                out_file.display()
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_extensions_are_case_insensitive() {
+        let output_directory: PathBuf =
+            std::env::temp_dir().join(format!("sayo_pbr_rs_{}_output", std::process::id()));
+        fs::create_dir_all(&output_directory).unwrap();
+        let file_names = FileNames {
+            scene_file_name: PathBuf::from("scene.toml"),
+            output_directory: output_directory.clone(),
+            ldr_output_file: Some(PathBuf::from("render.PNG")),
+            hdr_output_file: Some(PathBuf::from("render.PFM")),
+            overwrite_output_files: true,
+            exr_settings: ExrSettings::default(),
+        };
+        let mut film = Film::default();
+        film.new_film(2, 1, 45.0);
+
+        write_output(&file_names, film, ImageBuffer::new(2)).unwrap();
+        let written: Vec<bool> = ["render.PNG", "render.PFM"]
+            .iter()
+            .map(|name| output_directory.join(name).exists())
+            .collect();
+        fs::remove_dir_all(&output_directory).unwrap();
+        assert_eq!(written, [true, true]);
+    }
 }
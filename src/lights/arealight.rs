@@ -0,0 +1,82 @@
+use crate::common::*;
+use crate::geometry::SampleableShape;
+use crate::lights::{Light, LightSample};
+use crate::textures::Texture;
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+//Diffuse emitter covering the surface of a shape, emitting only on the side the normal points to
+pub struct AreaLight {
+    shape: Arc<dyn SampleableShape>,
+    emission: Arc<dyn Texture>,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn SampleableShape>, emission: Arc<dyn Texture>) -> AreaLight {
+        AreaLight { shape, emission }
+    }
+}
+
+impl fmt::Debug for AreaLight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AreaLight")
+            .field("emission", &self.emission)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, reference_point: Point3, u: Point2) -> Option<LightSample> {
+        let surface_sample = self.shape.sample_surface(u);
+        let to_light: Vec3 = surface_sample.point - reference_point;
+        let distance_squared: fp = to_light.length_squared();
+        if distance_squared == 0.0 || surface_sample.pdf == 0.0 {
+            return None;
+        }
+        let distance: fp = distance_squared.sqrt();
+        let wi: Vec3 = to_light / distance;
+        let cos_theta_light: fp = surface_sample.normal.dot(-wi);
+        if cos_theta_light <= 0.0 {
+            return None;
+        }
+        //Convert the area density to solid angle
+        Some(LightSample {
+            wi,
+            li: self.emission.evaluate(surface_sample.uv),
+            pdf: surface_sample.pdf * distance_squared / cos_theta_light,
+            distance,
+        })
+    }
+
+    fn pdf_li(&self, reference_point: Point3, wi: Vec3) -> fp {
+        let ray = Ray::new(reference_point, wi, 0.0, fp::INFINITY);
+        match self
+            .shape
+            .check_intersection_and_return_closest_hit(ray, 0.0, fp::MAX)
+        {
+            Some(intersection_info) => {
+                let cos_theta_light: fp = fp::abs(intersection_info.normal.dot(wi));
+                if cos_theta_light == 0.0 {
+                    return 0.0;
+                }
+                let distance_squared: fp =
+                    (intersection_info.point_of_intersection - reference_point).length_squared();
+                self.shape.pdf_surface(&intersection_info) * distance_squared / cos_theta_light
+            }
+            None => 0.0,
+        }
+    }
+
+    fn l(&self, intersection_info: &IntersectionInfo, w: Vec3) -> Spectrum {
+        if intersection_info.normal.dot(w) <= 0.0 {
+            return Spectrum::default();
+        }
+        self.emission.evaluate(intersection_info.uv)
+    }
+
+    //Diffuse emission over one side of the surface
+    fn power(&self, _scene_radius: fp) -> Spectrum {
+        self.emission.average() * (PI * self.shape.area())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::common::*;
+use crate::lights::{Light, LightSample};
+use std::f64::consts::PI;
+
+//Parallel light arriving from infinitely far away, like sunlight
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    //Direction the light travels in, normalized
+    direction: Vec3,
+    //Irradiance on a surface facing the light
+    irradiance: Spectrum,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Spectrum) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _reference_point: Point3, _u: Point2) -> Option<LightSample> {
+        Some(LightSample {
+            wi: -self.direction,
+            li: self.irradiance,
+            pdf: 1.0,
+            distance: fp::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _reference_point: Point3, _wi: Vec3) -> fp {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    //Light falling onto a disk as large as the scene
+    fn power(&self, scene_radius: fp) -> Spectrum {
+        self.irradiance * (PI * scene_radius * scene_radius)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::common::*;
+use crate::lights::{Light, LightSample};
+use crate::textures::bitmaptexture::BitmapTexture;
+use crate::textures::Texture;
+use crate::utilities::imageutils::HdrImage;
+use crate::utilities::sampling::{uniform_sample_sphere, uniform_sphere_pdf, Distribution2D};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+//Radiance arriving from every direction, looked up in a latitude-longitude image. The top row of
+//the image is straight up (+y in light space), u goes around the y axis starting at -x.
+#[derive(Debug, Clone)]
+pub struct EnvironmentLight {
+    radiance: BitmapTexture,
+    light_to_world: Transform,
+    //Importance sampling by image luminance, uniform sphere sampling when not set
+    distribution: Option<Distribution2D>,
+    //Mean radiance over the sphere of directions
+    average_radiance: Spectrum,
+}
+
+impl EnvironmentLight {
+    pub fn new(image: Arc<HdrImage>, light_to_world: Transform, sample: bool) -> EnvironmentLight {
+        //Rows cover less solid angle towards the poles, weight them by sin(theta)
+        let mut weighted_sum: Spectrum = Spectrum::default();
+        let mut weight_sum: fp = 0.0;
+        let mut func: Vec<fp> = Vec::with_capacity(image.pixels.len());
+        for y in 0..image.height {
+            let sin_theta: fp = (PI * (fp::from(y) + 0.5) / fp::from(image.height)).sin();
+            for x in 0..image.width {
+                let radiance: Spectrum = image.get(x, y);
+                weighted_sum += radiance * sin_theta;
+                weight_sum += sin_theta;
+                func.push(radiance.luminance().max(0.0) * sin_theta);
+            }
+        }
+        let distribution =
+            sample.then(|| Distribution2D::new(&func, image.width as usize, image.height as usize));
+        EnvironmentLight {
+            radiance: BitmapTexture::new(image),
+            light_to_world,
+            distribution,
+            average_radiance: weighted_sum / weight_sum,
+        }
+    }
+
+    //Image coordinates of a light space direction, with v = theta / pi counted from the top row
+    fn direction_to_image(w: Vec3) -> Point2 {
+        let phi: fp = w.z.atan2(w.x);
+        let theta: fp = w.y.clamp(-1.0, 1.0).acos();
+        Point2::new(phi / (2.0 * PI) + 0.5, theta / PI)
+    }
+
+    fn image_to_direction(p: Point2) -> Vec3 {
+        let phi: fp = (p.x - 0.5) * 2.0 * PI;
+        let (sin_theta, cos_theta) = (p.y * PI).sin_cos();
+        Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin())
+    }
+
+    fn lookup(&self, p: Point2) -> Spectrum {
+        //Bitmap textures have v pointing up
+        self.radiance.evaluate(Point2::new(p.x, 1.0 - p.y))
+    }
+
+    fn world_to_light(&self, w: Vec3) -> Vec3 {
+        self.light_to_world
+            .inverse()
+            .transform_vector(w)
+            .normalize()
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample_li(&self, _reference_point: Point3, u: Point2) -> Option<LightSample> {
+        let (wi, pdf) = match &self.distribution {
+            Some(distribution) => {
+                let (p, image_pdf) = distribution.sample_continuous(u);
+                let sin_theta: fp = (p.y * PI).sin();
+                if image_pdf == 0.0 || sin_theta == 0.0 {
+                    return None;
+                }
+                //From density over the image to density over solid angle
+                (
+                    EnvironmentLight::image_to_direction(p),
+                    image_pdf / (2.0 * PI * PI * sin_theta),
+                )
+            }
+            None => (uniform_sample_sphere(u), uniform_sphere_pdf()),
+        };
+        Some(LightSample {
+            wi: self.light_to_world.transform_vector(wi).normalize(),
+            li: self.lookup(EnvironmentLight::direction_to_image(wi)),
+            pdf,
+            distance: fp::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _reference_point: Point3, wi: Vec3) -> fp {
+        match &self.distribution {
+            Some(distribution) => {
+                let p: Point2 = EnvironmentLight::direction_to_image(self.world_to_light(wi));
+                let sin_theta: fp = (p.y * PI).sin();
+                if sin_theta == 0.0 {
+                    return 0.0;
+                }
+                distribution.pdf(p) / (2.0 * PI * PI * sin_theta)
+            }
+            None => uniform_sphere_pdf(),
+        }
+    }
+
+    fn le(&self, ray: &Ray) -> Spectrum {
+        self.lookup(EnvironmentLight::direction_to_image(
+            self.world_to_light(ray.d),
+        ))
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    //Radiance from all directions falling onto a disk as large as the scene
+    fn power(&self, scene_radius: fp) -> Spectrum {
+        self.average_radiance * (4.0 * PI * PI * scene_radius * scene_radius)
+    }
+}
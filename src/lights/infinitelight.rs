@@ -0,0 +1,44 @@
+use crate::common::*;
+use crate::lights::{Light, LightSample};
+use crate::utilities::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
+use std::f64::consts::PI;
+
+//Constant radiance arriving from every direction, used as the background of the scene
+#[derive(Debug, Clone)]
+pub struct UniformInfiniteLight {
+    radiance: Spectrum,
+}
+
+impl UniformInfiniteLight {
+    pub fn new(radiance: Spectrum) -> UniformInfiniteLight {
+        UniformInfiniteLight { radiance }
+    }
+}
+
+impl Light for UniformInfiniteLight {
+    fn sample_li(&self, _reference_point: Point3, u: Point2) -> Option<LightSample> {
+        Some(LightSample {
+            wi: uniform_sample_sphere(u),
+            li: self.radiance,
+            pdf: uniform_sphere_pdf(),
+            distance: fp::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _reference_point: Point3, _wi: Vec3) -> fp {
+        uniform_sphere_pdf()
+    }
+
+    fn le(&self, _ray: &Ray) -> Spectrum {
+        self.radiance
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    //Radiance from all directions falling onto a disk as large as the scene
+    fn power(&self, scene_radius: fp) -> Spectrum {
+        self.radiance * (4.0 * PI * PI * scene_radius * scene_radius)
+    }
+}
@@ -0,0 +1,53 @@
+use crate::common::*;
+use std::fmt::Debug;
+
+pub mod arealight;
+pub mod directionallight;
+pub mod environmentlight;
+pub mod infinitelight;
+pub mod pointlight;
+pub mod spotlight;
+
+pub struct LightSample {
+    //Direction from the reference point towards the light
+    pub wi: Vec3,
+    //Incident radiance along wi, not accounting for occlusion
+    pub li: Spectrum,
+    //Density of sampling wi, with respect to solid angle. Delta lights have a single possible
+    //direction and report 1.
+    pub pdf: fp,
+    //Distance to the sampled point on the light, infinite for lights at infinity
+    pub distance: fp,
+}
+
+pub trait Light: Debug + Send + Sync {
+    //Sample a direction from the reference point towards the light
+    fn sample_li(&self, reference_point: Point3, u: Point2) -> Option<LightSample>;
+
+    //Solid angle density with which sample_li would have picked wi from the reference point
+    fn pdf_li(&self, reference_point: Point3, wi: Vec3) -> fp;
+
+    //Radiance carried by a ray that leaves the scene without hitting anything
+    fn le(&self, _ray: &Ray) -> Spectrum {
+        Spectrum::default()
+    }
+
+    //Radiance emitted from a point on the light's surface in direction w
+    fn l(&self, _intersection_info: &IntersectionInfo, _w: Vec3) -> Spectrum {
+        Spectrum::default()
+    }
+
+    fn is_infinite(&self) -> bool {
+        false
+    }
+
+    //Lights described by a delta distribution, a single point or direction, which rays can never
+    //hit and which can only be reached by light sampling
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    //Total emitted power, used to pick lights in proportion to it. Lights at infinity need the
+    //radius of the scene they shine on.
+    fn power(&self, scene_radius: fp) -> Spectrum;
+}
@@ -0,0 +1,49 @@
+use crate::common::*;
+use crate::lights::{Light, LightSample};
+use std::f64::consts::PI;
+
+//Isotropic emitter at a single point
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    position: Point3,
+    //Radiant intensity, power per unit solid angle
+    intensity: Spectrum,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Spectrum) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, reference_point: Point3, _u: Point2) -> Option<LightSample> {
+        let to_light: Vec3 = self.position - reference_point;
+        let distance_squared: fp = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance: fp = distance_squared.sqrt();
+        Some(LightSample {
+            wi: to_light / distance,
+            li: self.intensity / distance_squared,
+            pdf: 1.0,
+            distance,
+        })
+    }
+
+    fn pdf_li(&self, _reference_point: Point3, _wi: Vec3) -> fp {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn power(&self, _scene_radius: fp) -> Spectrum {
+        self.intensity * (4.0 * PI)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::common::*;
+use crate::lights::{Light, LightSample};
+use std::f64::consts::PI;
+
+//Point emitter shining into a cone, at full intensity up to falloff_start and fading out towards
+//the edge of the cone
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    position: Point3,
+    //Axis of the cone, normalized
+    direction: Vec3,
+    //Radiant intensity along the axis
+    intensity: Spectrum,
+    cos_cone_angle: fp,
+    cos_falloff_start: fp,
+}
+
+impl SpotLight {
+    //Angles in degrees, measured from the axis of the cone
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Spectrum,
+        cone_angle_degrees: fp,
+        falloff_start_degrees: fp,
+    ) -> SpotLight {
+        let cone_angle: fp = cone_angle_degrees.to_radians();
+        let falloff_start: fp = fp::min(falloff_start_degrees.to_radians(), cone_angle);
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_cone_angle: cone_angle.cos(),
+            cos_falloff_start: falloff_start.cos(),
+        }
+    }
+
+    //pbrt's smooth quartic falloff between the two cone angles
+    fn falloff(&self, w: Vec3) -> fp {
+        let cos_theta: fp = w.dot(self.direction);
+        if cos_theta < self.cos_cone_angle {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let delta: fp =
+            (cos_theta - self.cos_cone_angle) / (self.cos_falloff_start - self.cos_cone_angle);
+        (delta * delta) * (delta * delta)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, reference_point: Point3, _u: Point2) -> Option<LightSample> {
+        let to_light: Vec3 = self.position - reference_point;
+        let distance_squared: fp = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance: fp = distance_squared.sqrt();
+        let wi: Vec3 = to_light / distance;
+        let falloff: fp = self.falloff(-wi);
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            li: self.intensity * (falloff / distance_squared),
+            pdf: 1.0,
+            distance,
+        })
+    }
+
+    fn pdf_li(&self, _reference_point: Point3, _wi: Vec3) -> fp {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    //Full intensity over the inner cone, the falloff region counted at half
+    fn power(&self, _scene_radius: fp) -> Spectrum {
+        self.intensity * (2.0 * PI * (1.0 - 0.5 * (self.cos_falloff_start + self.cos_cone_angle)))
+    }
+}
@@ -1,79 +1,128 @@
 use flexi_logger::{with_thread, Logger};
 use log::{info, warn};
-use ndarray::Array2;
 use sayo_pbr_rs::accel::bvh_node::BvhNode;
+use sayo_pbr_rs::cli::{CliError, CommandLine, PreviewKind, USAGE};
 use sayo_pbr_rs::common::*;
 use sayo_pbr_rs::integrators::baseintegrator::*;
 use sayo_pbr_rs::integrators::Integrator;
-use sayo_pbr_rs::{write_output, ImageBuffer, SceneCamera, SceneConfig, SceneGeometries};
+use sayo_pbr_rs::preview::tevpreview::TevPreview;
+use sayo_pbr_rs::preview::{NoPreview, PreviewSink};
+use sayo_pbr_rs::{
+    write_output, ImageBuffer, SceneCamera, SceneConfig, SceneGeometries, SceneLights,
+    SceneMaterials,
+};
 use std::error::Error;
 use std::sync::Arc;
-use std::sync::Mutex;
 use std::time::Instant;
-use tev_client::PacketCreateImage;
-use tev_client::TevClient;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    Logger::try_with_env_or_str("info")?
-        .format(with_thread)
-        .start()
-        .unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command_line = match CommandLine::parse(&args) {
+        Ok(command_line) => command_line,
+        Err(CliError::HelpRequested) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(CliError::Invalid(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let mut args: Vec<String> = std::env::args().collect();
-
-    let current_dir = std::env::current_dir().unwrap();
-    info!("{:?}", std::fs::canonicalize(current_dir).unwrap());
-
-    //current_dir() is the root directory of the project, setting relative paths
-    //If no arguments specified, try to use a default scene
-    if args.len() == 1 {
-        //let scene_file_path = "scenes/dragon/dragon_scene.toml".to_string();
-        args.push("--release".to_string());
-        let scene_file_path = "scenes/teapot/teapot_test_scene.toml".to_string();
-        //let scene_file_path = "scenes/simple_cube/simple_cube_scene.toml".to_string();
-        args.push(scene_file_path);
-    }
+    //An explicit --log-level wins over RUST_LOG
+    let logger = match &command_line.log_level {
+        Some(log_level) => Logger::try_with_str(log_level)?,
+        None => Logger::try_with_env_or_str("info")?,
+    };
+    logger.format(with_thread).start()?;
     info!("{:?}", &args);
 
-    let mut tev_client = TevClient::spawn_path_default()?;
+    if let Some(threads) = command_line.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
 
     let mut start = Instant::now();
-    let scene_config_tuple = SceneConfig::parse_args(&args);
-    let scene_filename = scene_config_tuple.0;
-    let parsed_scene_config = scene_config_tuple.1;
-    let (scene_config, file_names) =
-        SceneConfig::construct_scene(scene_filename.clone(), parsed_scene_config.clone()).unwrap();
-    let scene_camera = SceneCamera::construct_camera(parsed_scene_config.clone());
-    let scene_geometries =
-        SceneGeometries::construct_geometries(scene_filename, parsed_scene_config.clone());
-    let film = SceneConfig::construct_film(parsed_scene_config);
+    let scene_filename = command_line.scene_file.clone();
+    let parsed_scene_config = SceneConfig::load_scene_file(&scene_filename)?;
+
+    //Live preview in tev is opt in, tev has to be running already
+    let preview: Arc<dyn PreviewSink> = match command_line.preview {
+        PreviewKind::Tev => {
+            let image_name = scene_filename
+                .file_stem()
+                .map_or("render".into(), |stem| stem.to_string_lossy());
+            match TevPreview::connect(&command_line.tev_address, &image_name) {
+                Ok(tev_preview) => Arc::new(tev_preview),
+                Err(e) => {
+                    warn!(
+                        "Warning: could not connect to tev at {} ({}), rendering without preview...",
+                        command_line.tev_address, e
+                    );
+                    Arc::new(NoPreview)
+                }
+            }
+        }
+        PreviewKind::None => Arc::new(NoPreview),
+    };
+    let (mut scene_config, mut file_names) =
+        SceneConfig::construct_scene(scene_filename.clone(), parsed_scene_config.clone())?;
+    //Command line overrides
+    if let Some(integrator) = command_line.integrator {
+        scene_config.integrator = integrator;
+    }
+    if let Some(samples_count) = command_line.samples_count {
+        scene_config.settings.samples_count = samples_count;
+    }
+    scene_config.seed = command_line.seed;
+    if let Some(output_directory) = &command_line.output_directory {
+        file_names.output_directory = output_directory.clone();
+    }
+    //A file named on the command line is relative to the working directory, not the scene
+    if let Some(hdr_output_file) = &command_line.hdr_output_file {
+        file_names.hdr_output_file = Some(std::env::current_dir()?.join(hdr_output_file));
+    }
+    if let Some(ldr_output_file) = &command_line.ldr_output_file {
+        file_names.ldr_output_file = Some(std::env::current_dir()?.join(ldr_output_file));
+    }
+    let scene_camera = SceneCamera::construct_camera(&scene_filename, parsed_scene_config.clone())?;
+    let scene_materials =
+        SceneMaterials::construct_materials(&scene_filename, parsed_scene_config.clone())?;
+    let scene_geometries = SceneGeometries::construct_geometries(
+        scene_filename.clone(),
+        parsed_scene_config.clone(),
+        &scene_materials,
+    )?;
+    let scene_lights = SceneLights::construct_lights(
+        &scene_filename,
+        parsed_scene_config.clone(),
+        &scene_geometries,
+    )?;
+    let mut film = SceneConfig::construct_film(&scene_filename, parsed_scene_config)?;
+    if let Some((width, height)) = command_line.resolution {
+        let fov_degrees = film.fov.to_degrees();
+        film.new_film(width, height, fov_degrees);
+    }
     let duration_init = start.elapsed();
     warn!("Time to init scene: {:?}", duration_init);
     let root_bvh = BvhNode::construct_bvh(scene_geometries.geometries.clone(), 0);
     let duration_bvh = start.elapsed();
     warn!("Time to create BVH: {:?}", duration_bvh);
 
-    // Initalize tev client
-    tev_client.send(PacketCreateImage {
-        image_name: "test",
-        grab_focus: false,
-        width: film.width as u32,
-        height: film.height as u32,
-        channel_names: &["R", "G", "B"],
-    })?;
+    // Initalize preview if any
+    preview.create_image(film.width, film.height);
 
     start = Instant::now();
-    let tiles: Array2<Spectrum> = BaseIntegrator::render(
+    let tiles = BaseIntegrator::render(
         Arc::new(scene_config),
-        1,
-        1,
         Arc::new(scene_camera),
         root_bvh,
         //Arc::new(scene_geometries),
+        Arc::new(scene_lights),
         Arc::new(film.clone()),
-        1e-5,
         fp::MAX,
-        Arc::new(Mutex::new(tev_client)),
+        preview,
     );
 
     let mut image_buffer = ImageBuffer::new((film.height * film.width) as usize);
@@ -82,7 +131,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let duration = start.elapsed();
     warn!("Total time taken: {:?}", duration);
 
-    write_output(file_names.out_file, film, image_buffer)?;
+    write_output(&file_names, film, image_buffer)?;
 
     Ok(())
 }
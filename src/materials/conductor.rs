@@ -0,0 +1,48 @@
+use crate::common::*;
+use crate::materials::fresnel::fresnel_conductor;
+use crate::materials::{Bsdf, BsdfSample};
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Perfectly smooth metal, a mirror tinted by the Fresnel reflectance of its complex IOR
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    //Extra tint on top of the Fresnel reflectance
+    albedo: Arc<dyn Texture>,
+    eta: Spectrum,
+    k: Spectrum,
+}
+
+impl Conductor {
+    pub fn new(albedo: Arc<dyn Texture>, eta: Spectrum, k: Spectrum) -> Conductor {
+        Conductor { albedo, eta, k }
+    }
+}
+
+impl Bsdf for Conductor {
+    //A delta lobe, no pair of directions chosen independently can ever be the mirror pair
+    fn eval(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> Spectrum {
+        Spectrum::default()
+    }
+
+    fn sample(&self, wo: Vec3, uv: Point2, _u_component: fp, _u: Point2) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wi: Vec3 = Vec3::new(-wo.x, -wo.y, wo.z);
+        Some(BsdfSample {
+            wi,
+            f: self.albedo.evaluate(uv) * fresnel_conductor(wo.z, self.eta, self.k) / wi.z,
+            pdf: 1.0,
+            is_specular: true,
+        })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> fp {
+        0.0
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv) * fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::common::*;
+use crate::materials::fresnel::fresnel_dielectric;
+use crate::materials::{refract, Bsdf, BsdfSample};
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Smooth boundary of a transparent medium such as glass, reflecting and refracting in proportion to
+//the Fresnel reflectance
+#[derive(Debug, Clone)]
+pub struct Dielectric {
+    //Tint applied to both reflection and refraction
+    albedo: Arc<dyn Texture>,
+    //IOR inside over IOR outside
+    ior: fp,
+}
+
+impl Dielectric {
+    pub fn new(albedo: Arc<dyn Texture>, ior: fp) -> Dielectric {
+        Dielectric { albedo, ior }
+    }
+}
+
+impl Bsdf for Dielectric {
+    //Both lobes are deltas
+    fn eval(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> Spectrum {
+        Spectrum::default()
+    }
+
+    //u_component picks reflection with the probability of the Fresnel reflectance
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, _u: Point2) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let fresnel: fp = fresnel_dielectric(wo.z, self.ior);
+        let albedo: Spectrum = self.albedo.evaluate(uv);
+        if u_component < fresnel {
+            let wi: Vec3 = Vec3::new(-wo.x, -wo.y, wo.z);
+            return Some(BsdfSample {
+                wi,
+                f: albedo * (fresnel / wi.z.abs()),
+                pdf: fresnel,
+                is_specular: true,
+            });
+        }
+
+        let entering: bool = wo.z > 0.0;
+        let (eta_o, eta_i) = if entering {
+            (1.0, self.ior)
+        } else {
+            (self.ior, 1.0)
+        };
+        let normal: Vec3 = Vec3::new(0.0, 0.0, wo.z.signum());
+        let wi: Vec3 = refract(wo, normal, eta_o / eta_i)?;
+        //Radiance is compressed into a smaller solid angle on the denser side
+        let scale: fp = (eta_o * eta_o) / (eta_i * eta_i);
+        Some(BsdfSample {
+            wi,
+            f: albedo * ((1.0 - fresnel) * scale / wi.z.abs()),
+            pdf: 1.0 - fresnel,
+            is_specular: true,
+        })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> fp {
+        0.0
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv)
+    }
+
+    fn has_transmission(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,157 @@
+use crate::common::*;
+use std::f64::consts::PI;
+
+//Wavelengths in nm standing in for the red, green and blue channels in interference effects
+const RGB_WAVELENGTHS: [fp; 3] = [650.0, 510.0, 475.0];
+
+//Unpolarized Fresnel reflectance of the boundary between outside and a dielectric with the given
+//IOR. cos_theta_i is measured against the outward normal, negative when arriving from inside.
+pub fn fresnel_dielectric(cos_theta_i: fp, ior: fp) -> fp {
+    let (cos_theta_i, eta) = if cos_theta_i < 0.0 {
+        (-cos_theta_i, 1.0 / ior)
+    } else {
+        (cos_theta_i, ior)
+    };
+    let cos_theta_i: fp = cos_theta_i.min(1.0);
+    let sin2_theta_t: fp = (1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    //Total internal reflection
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t: fp = (1.0 - sin2_theta_t).sqrt();
+    let r_parallel: fp = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular: fp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+//Fraction of light spread diffusely inside a dielectric that is reflected back in at the boundary,
+//the Fresnel reflectance from inside averaged over the cosine weighted hemisphere
+pub fn diffuse_fresnel(ior: fp) -> fp {
+    //Trapezoidal rule over cos^2(theta), which is uniform for cosine weighted directions
+    const STEPS: usize = 1000;
+    let mut sum: fp = 0.0;
+    let mut previous: fp = fresnel_dielectric(0.0, ior);
+    for i in 1..=STEPS {
+        let cos_theta: fp = (i as fp / STEPS as fp).sqrt();
+        let current: fp = fresnel_dielectric(-cos_theta, ior);
+        sum += 0.5 * (previous + current) / STEPS as fp;
+        previous = current;
+    }
+    sum
+}
+
+//Amplitude reflection coefficients for s and p polarized light entering a dielectric from outside,
+//along with the cosine of the refracted angle. None on total internal reflection.
+fn dielectric_amplitudes(cos_theta_i: fp, ior: fp) -> Option<(fp, fp, fp)> {
+    let cos_theta_i: fp = cos_theta_i.abs().min(1.0);
+    let sin2_theta_t: fp = (1.0 - cos_theta_i * cos_theta_i) / (ior * ior);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t: fp = (1.0 - sin2_theta_t).sqrt();
+    let r_s: fp = (cos_theta_i - ior * cos_theta_t) / (cos_theta_i + ior * cos_theta_t);
+    let r_p: fp = (ior * cos_theta_i - cos_theta_t) / (ior * cos_theta_i + cos_theta_t);
+    Some((r_s, r_p, cos_theta_t))
+}
+
+//Reflectance of a thin dielectric layer surrounded by air, summed over all internal reflections
+//without interference, and the cosine of the angle inside the layer (0 on total internal
+//reflection)
+pub fn thin_film_reflectance(cos_theta_i: fp, ior: fp) -> (fp, fp) {
+    let (r_s, r_p, cos_theta_t) = match dielectric_amplitudes(cos_theta_i, ior) {
+        Some(amplitudes) => amplitudes,
+        None => return (1.0, 0.0),
+    };
+    //A layer with interface reflectance R reflects 2R / (1 + R) in total
+    let layer = |r: fp| 2.0 * r * r / (1.0 + r * r);
+    (0.5 * (layer(r_s) + layer(r_p)), cos_theta_t)
+}
+
+//Reflectance of a thin dielectric layer of the given thickness in nm surrounded by air, with the
+//waves reflected inside the layer interfering, and the cosine of the angle inside the layer
+pub fn thin_film_reflectance_interference(
+    cos_theta_i: fp,
+    ior: fp,
+    thickness_nm: fp,
+) -> (Spectrum, fp) {
+    let (r_s, r_p, cos_theta_t) = match dielectric_amplitudes(cos_theta_i, ior) {
+        Some(amplitudes) => amplitudes,
+        None => return (Spectrum::from(1.0), 0.0),
+    };
+    //Airy summation for a layer with the same medium on both sides
+    let layer = |r: fp, cos_delta: fp| {
+        let r2: fp = r * r;
+        2.0 * r2 * (1.0 - cos_delta) / (1.0 + r2 * r2 - 2.0 * r2 * cos_delta)
+    };
+    let reflectance = |wavelength: fp| {
+        let cos_delta: fp = (4.0 * PI * ior * thickness_nm * cos_theta_t / wavelength).cos();
+        0.5 * (layer(r_s, cos_delta) + layer(r_p, cos_delta))
+    };
+    (
+        Spectrum::new(
+            reflectance(RGB_WAVELENGTHS[0]),
+            reflectance(RGB_WAVELENGTHS[1]),
+            reflectance(RGB_WAVELENGTHS[2]),
+        ),
+        cos_theta_t,
+    )
+}
+
+//Unpolarized Fresnel reflectance of a conductor with complex index of refraction eta + ik, seen
+//from outside at the given angle
+pub fn fresnel_conductor(cos_theta_i: fp, eta: Spectrum, k: Spectrum) -> Spectrum {
+    Spectrum::new(
+        fresnel_conductor_channel(cos_theta_i, eta.x, k.x),
+        fresnel_conductor_channel(cos_theta_i, eta.y, k.y),
+        fresnel_conductor_channel(cos_theta_i, eta.z, k.z),
+    )
+}
+
+fn fresnel_conductor_channel(cos_theta_i: fp, eta: fp, k: fp) -> fp {
+    let cos_theta_i: fp = cos_theta_i.clamp(0.0, 1.0);
+    let cos2_theta_i: fp = cos_theta_i * cos_theta_i;
+    let sin2_theta_i: fp = 1.0 - cos2_theta_i;
+    let eta2: fp = eta * eta;
+    let k2: fp = k * k;
+
+    let t0: fp = eta2 - k2 - sin2_theta_i;
+    let a2_plus_b2: fp = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1: fp = a2_plus_b2 + cos2_theta_i;
+    let a: fp = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2: fp = 2.0 * cos_theta_i * a;
+    let r_s: fp = (t1 - t2) / (t1 + t2);
+
+    let t3: fp = cos2_theta_i * a2_plus_b2 + sin2_theta_i * sin2_theta_i;
+    let t4: fp = t2 * sin2_theta_i;
+    let r_p: fp = r_s * (t3 - t4) / (t3 + t4);
+    0.5 * (r_s + r_p)
+}
+
+//Measured complex index of refraction (eta, k) of common metals, averaged over the red, green and
+//blue parts of the visible spectrum
+pub fn conductor_preset(name: &str) -> Option<(Spectrum, Spectrum)> {
+    let (eta, k) = match name {
+        "Au" => (
+            Spectrum::new(0.1431189557, 0.3749570432, 1.4424785571),
+            Spectrum::new(3.9831604247, 2.3857207478, 1.6032152899),
+        ),
+        "Ag" => (
+            Spectrum::new(0.1552646489, 0.1167232965, 0.1383806959),
+            Spectrum::new(4.8283433224, 3.1222459278, 2.1469504455),
+        ),
+        "Cu" => (
+            Spectrum::new(0.2004376970, 0.9240334304, 1.1022119527),
+            Spectrum::new(3.9129485033, 2.4528477015, 2.1421879552),
+        ),
+        "Al" => (
+            Spectrum::new(1.6574599595, 0.8803689579, 0.5212287346),
+            Spectrum::new(9.2238691996, 6.2695232477, 4.8370012281),
+        ),
+        "Cr" => (
+            Spectrum::new(4.3696828663, 2.9167024892, 1.6547005413),
+            Spectrum::new(5.2064337956, 4.2313645277, 3.7549467933),
+        ),
+        _ => return None,
+    };
+    Some((eta, k))
+}
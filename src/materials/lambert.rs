@@ -0,0 +1,51 @@
+use crate::common::*;
+use crate::materials::{Bsdf, BsdfSample};
+use crate::textures::Texture;
+use crate::utilities::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use std::f64::consts::FRAC_1_PI;
+use std::sync::Arc;
+
+//Ideal diffuse reflector
+#[derive(Debug, Clone)]
+pub struct Lambert {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Lambert {
+    pub fn new(albedo: Arc<dyn Texture>) -> Lambert {
+        Lambert { albedo }
+    }
+}
+
+impl Bsdf for Lambert {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Spectrum::default();
+        }
+        self.albedo.evaluate(uv) * FRAC_1_PI
+    }
+
+    fn sample(&self, wo: Vec3, uv: Point2, _u_component: fp, u: Point2) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wi: Vec3 = cosine_sample_hemisphere(u);
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf: cosine_hemisphere_pdf(wi.z),
+            is_specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, _uv: Point2) -> fp {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        cosine_hemisphere_pdf(wi.z)
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv)
+    }
+}
@@ -0,0 +1,234 @@
+use crate::common::*;
+use std::f64::consts::PI;
+
+//Distributions of microfacet normals, in the local shading space. alpha is the roughness parameter
+//of the distribution, not the perceptual roughness. GGX may be stretched differently along the x
+//and y axes of the shading space.
+#[derive(Debug, Clone, Copy)]
+pub enum MicrofacetDistribution {
+    Ggx { alpha_x: fp, alpha_y: fp },
+    Beckmann { alpha: fp },
+}
+
+impl MicrofacetDistribution {
+    //Distribution names as used in scene files
+    pub fn from_name(name: &str, alpha: fp) -> Option<MicrofacetDistribution> {
+        //Perfectly smooth surfaces have no distribution, keep the math finite
+        let alpha: fp = alpha.max(1e-4);
+        match name.to_ascii_lowercase().as_str() {
+            "ggx" => Some(MicrofacetDistribution::Ggx {
+                alpha_x: alpha,
+                alpha_y: alpha,
+            }),
+            "beckmann" => Some(MicrofacetDistribution::Beckmann { alpha }),
+            _ => None,
+        }
+    }
+
+    //Density of microfacet normals wh, projected onto the macro surface
+    pub fn d(&self, wh: Vec3) -> fp {
+        let cos2_theta: fp = wh.z * wh.z;
+        if cos2_theta <= 0.0 {
+            return 0.0;
+        }
+        match *self {
+            MicrofacetDistribution::Ggx { alpha_x, alpha_y } => {
+                let x: fp = wh.x / alpha_x;
+                let y: fp = wh.y / alpha_y;
+                let denominator: fp = x * x + y * y + cos2_theta;
+                1.0 / (PI * alpha_x * alpha_y * denominator * denominator)
+            }
+            MicrofacetDistribution::Beckmann { alpha } => {
+                let alpha2: fp = alpha * alpha;
+                let tan2_theta: fp = (1.0 - cos2_theta) / cos2_theta;
+                (-tan2_theta / alpha2).exp() / (PI * alpha2 * cos2_theta * cos2_theta)
+            }
+        }
+    }
+
+    //Smith's auxiliary function, the masked area per unit visible area
+    fn lambda(&self, w: Vec3) -> fp {
+        let cos2_theta: fp = w.z * w.z;
+        let tan2_theta: fp = (1.0 - cos2_theta).max(0.0) / cos2_theta;
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+        match *self {
+            MicrofacetDistribution::Ggx { alpha_x, alpha_y } => {
+                let x: fp = alpha_x * w.x;
+                let y: fp = alpha_y * w.y;
+                0.5 * ((1.0 + (x * x + y * y) / cos2_theta).sqrt() - 1.0)
+            }
+            //Walter et al.'s rational approximation
+            MicrofacetDistribution::Beckmann { alpha } => {
+                let a: fp = 1.0 / (alpha * tan2_theta.sqrt());
+                if a >= 1.6 {
+                    return 0.0;
+                }
+                (1.0 - 1.259 * a + 0.396 * a * a) / (3.535 * a + 2.181 * a * a)
+            }
+        }
+    }
+
+    //Fraction of microfacets visible from w
+    pub fn g1(&self, w: Vec3) -> fp {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    //Height correlated masking and shadowing
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> fp {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    //Density of the normals visible from wo, with respect to solid angle
+    pub fn visible_normal_pdf(&self, wo: Vec3, wh: Vec3) -> fp {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * fp::max(0.0, wo.dot(wh)) * self.d(wh) / wo.z
+    }
+
+    //Samples a microfacet normal in proportion to how much of it is seen from wo, which has to be
+    //above the surface
+    pub fn sample_visible_normal(&self, wo: Vec3, u: Point2) -> Vec3 {
+        match *self {
+            MicrofacetDistribution::Ggx { alpha_x, alpha_y } => {
+                ggx_sample_visible_normal(wo, alpha_x, alpha_y, u)
+            }
+            MicrofacetDistribution::Beckmann { alpha } => {
+                beckmann_sample_visible_normal(wo, alpha, u)
+            }
+        }
+    }
+}
+
+//Heitz's 2018 method, sampling the projected area of a hemisphere in the stretched configuration
+fn ggx_sample_visible_normal(wo: Vec3, alpha_x: fp, alpha_y: fp, u: Point2) -> Vec3 {
+    let vh: Vec3 = Vec3::new(alpha_x * wo.x, alpha_y * wo.y, wo.z).normalize();
+    let length_squared: fp = vh.x * vh.x + vh.y * vh.y;
+    let t1: Vec3 = if length_squared > 0.0 {
+        Vec3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2: Vec3 = vh.cross(t1);
+
+    let r: fp = u.x.sqrt();
+    let phi: fp = 2.0 * PI * u.y;
+    let p1: fp = r * phi.cos();
+    let p2: fp = r * phi.sin();
+    let s: fp = 0.5 * (1.0 + vh.z);
+    let p2: fp = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * p2;
+
+    let nh: Vec3 = t1 * p1 + t2 * p2 + vh * fp::max(0.0, 1.0 - p1 * p1 - p2 * p2).sqrt();
+    Vec3::new(alpha_x * nh.x, alpha_y * nh.y, fp::max(1e-6, nh.z)).normalize()
+}
+
+//Jakob's method as in pbrt: sample the slopes of the unit roughness distribution seen from the
+//stretched direction, then rotate and unstretch them
+fn beckmann_sample_visible_normal(wo: Vec3, alpha: fp, u: Point2) -> Vec3 {
+    let wo_stretched: Vec3 = Vec3::new(alpha * wo.x, alpha * wo.y, wo.z).normalize();
+    let (slope_x, slope_y) = beckmann_sample_slopes(wo_stretched.z, u);
+
+    let sin_theta: fp = fp::max(0.0, 1.0 - wo_stretched.z * wo_stretched.z).sqrt();
+    let (cos_phi, sin_phi) = if sin_theta == 0.0 {
+        (1.0, 0.0)
+    } else {
+        (
+            (wo_stretched.x / sin_theta).clamp(-1.0, 1.0),
+            (wo_stretched.y / sin_theta).clamp(-1.0, 1.0),
+        )
+    };
+    let rotated_x: fp = cos_phi * slope_x - sin_phi * slope_y;
+    let rotated_y: fp = sin_phi * slope_x + cos_phi * slope_y;
+    Vec3::new(-alpha * rotated_x, -alpha * rotated_y, 1.0).normalize()
+}
+
+//Slopes of visible normals of a unit roughness Beckmann surface seen from cos_theta_i, with the
+//view direction in the xz plane
+fn beckmann_sample_slopes(cos_theta_i: fp, u: Point2) -> (fp, fp) {
+    //Normal incidence, the visible distribution is the distribution itself
+    if cos_theta_i > 0.9999 {
+        let r: fp = (-(1.0 - u.x).ln()).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * PI * u.y).sin_cos();
+        return (r * cos_phi, r * sin_phi);
+    }
+
+    //Invert the CDF of the x slope with a Newton-bisection search, starting from a fitted guess
+    let sin_theta_i: fp = fp::max(0.0, 1.0 - cos_theta_i * cos_theta_i).sqrt();
+    let tan_theta_i: fp = sin_theta_i / cos_theta_i;
+    let cot_theta_i: fp = 1.0 / tan_theta_i;
+    let sqrt_pi_inv: fp = 1.0 / PI.sqrt();
+
+    let mut a: fp = -1.0;
+    let mut c: fp = erf(cot_theta_i);
+    let sample_x: fp = fp::max(u.x, 1e-6);
+    let theta_i: fp = cos_theta_i.acos();
+    let fit: fp = 1.0 + theta_i * (-0.876 + theta_i * (0.4265 - 0.0594 * theta_i));
+    let mut b: fp = c - (1.0 + c) * (1.0 - sample_x).powf(fit);
+    let normalization: fp =
+        1.0 / (1.0 + c + sqrt_pi_inv * tan_theta_i * (-cot_theta_i * cot_theta_i).exp());
+
+    for _ in 0..10 {
+        if !(b >= a && b <= c) {
+            b = 0.5 * (a + c);
+        }
+        let inv_erf: fp = erf_inv(b);
+        let value: fp = normalization
+            * (1.0 + b + sqrt_pi_inv * tan_theta_i * (-inv_erf * inv_erf).exp())
+            - sample_x;
+        let derivative: fp = normalization * (1.0 - inv_erf * tan_theta_i);
+        if value.abs() < 1e-5 {
+            break;
+        }
+        if value > 0.0 {
+            c = b;
+        } else {
+            a = b;
+        }
+        b -= value / derivative;
+    }
+    (erf_inv(b), erf_inv(2.0 * fp::max(u.y, 1e-6) - 1.0))
+}
+
+//Abramowitz and Stegun 7.1.26
+fn erf(x: fp) -> fp {
+    let sign: fp = x.signum();
+    let x: fp = x.abs();
+    let t: fp = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial: fp = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - polynomial * (-x * x).exp())
+}
+
+//Giles' single precision approximation
+fn erf_inv(x: fp) -> fp {
+    let x: fp = x.clamp(-0.99999, 0.99999);
+    let mut w: fp = -((1.0 - x) * (1.0 + x)).ln();
+    let mut p: fp;
+    if w < 5.0 {
+        w -= 2.5;
+        p = 2.81022636e-08;
+        p = 3.43273939e-07 + p * w;
+        p = -3.5233877e-06 + p * w;
+        p = -4.39150654e-06 + p * w;
+        p = 0.00021858087 + p * w;
+        p = -0.00125372503 + p * w;
+        p = -0.00417768164 + p * w;
+        p = 0.246640727 + p * w;
+        p = 1.50140941 + p * w;
+    } else {
+        w = w.sqrt() - 3.0;
+        p = -0.000200214257;
+        p = 0.000100950558 + p * w;
+        p = 0.00134934322 + p * w;
+        p = -0.00367342844 + p * w;
+        p = 0.00573950773 + p * w;
+        p = -0.0076224613 + p * w;
+        p = 0.00943887047 + p * w;
+        p = 1.00167406 + p * w;
+        p = 2.83297682 + p * w;
+    }
+    p * x
+}
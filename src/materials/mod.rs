@@ -0,0 +1,68 @@
+use crate::common::*;
+use std::fmt::Debug;
+
+pub mod conductor;
+pub mod dielectric;
+pub mod fresnel;
+pub mod lambert;
+pub mod microfacet;
+pub mod mtl;
+pub mod plastic;
+pub mod principled;
+pub mod roughconductor;
+pub mod roughdielectric;
+pub mod roughplastic;
+pub mod thinsheet;
+
+//Albedo used when a scene leaves it unspecified, and for primitives without a material
+pub const DEFAULT_ALBEDO: fp = 0.5;
+
+pub struct BsdfSample {
+    //Sampled incident direction, in the local shading space
+    pub wi: Vec3,
+    //Value of the BSDF for the sampled pair of directions
+    pub f: Spectrum,
+    //Solid angle density of wi
+    pub pdf: fp,
+    //Sampled from a delta lobe such as a perfect mirror. f and pdf then both leave out the delta
+    //function and are only meaningful as a ratio, light sampling can never produce such a wi.
+    pub is_specular: bool,
+}
+
+//Scattering functions are evaluated in the local shading space, where the shading normal is +z
+//and wo points away from the surface, towards where the light is going
+pub trait Bsdf: Debug + Send + Sync {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum;
+
+    //u_component picks between lobes for BSDFs with more than one, u samples the chosen lobe
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, u: Point2) -> Option<BsdfSample>;
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, uv: Point2) -> fp;
+
+    //Overall reflectance, written to the albedo AOV
+    fn albedo(&self, uv: Point2) -> Spectrum;
+
+    //BSDFs letting light through the surface are shaded in the frame of the surface normal as is,
+    //so that wo.z < 0 means the ray arrived from inside. All others see wo.z > 0.
+    fn has_transmission(&self) -> bool {
+        false
+    }
+}
+
+//Mirror direction of w about the normal n
+pub fn reflect(w: Vec3, n: Vec3) -> Vec3 {
+    n * (2.0 * w.dot(n)) - w
+}
+
+//Direction of w after refraction through a surface with normal n on the same side as w, where eta
+//is the IOR on w's side over the IOR on the other side. None on total internal reflection.
+pub fn refract(w: Vec3, n: Vec3, eta: fp) -> Option<Vec3> {
+    let cos_theta_i: fp = n.dot(w);
+    let sin2_theta_i: fp = fp::max(0.0, 1.0 - cos_theta_i * cos_theta_i);
+    let sin2_theta_t: fp = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t: fp = (1.0 - sin2_theta_t).sqrt();
+    Some(-w * eta + n * (eta * cos_theta_i - cos_theta_t))
+}
@@ -0,0 +1,188 @@
+use crate::common::*;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambert::Lambert;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::principled::{PrincipledBsdf, PrincipledParameters};
+use crate::materials::roughdielectric::RoughDielectric;
+use crate::materials::{Bsdf, DEFAULT_ALBEDO};
+use crate::textures::bitmaptexture::BitmapTexture;
+use crate::textures::bumpmap::BumpMap;
+use crate::textures::constanttexture::ConstantTexture;
+use crate::textures::Texture;
+use crate::utilities::imageutils::read_image;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+//Material of a Wavefront .mtl file translated to the renderer's own, for meshes whose scene entry
+//does not name a bsdf
+#[derive(Debug, Clone)]
+pub struct MtlMaterial {
+    pub bsdf: Arc<dyn Bsdf>,
+    //From Ke, None when black
+    pub emission: Option<Arc<dyn Texture>>,
+    //From map_Bump or bump
+    pub bump_map: Option<Arc<BumpMap>>,
+}
+
+impl MtlMaterial {
+    //Transparent materials (d below 1, or illum 4, 6, 7 or 9) become glass with the IOR Ni, since
+    //partial dissolve is not supported. Materials without a specular colour, or with illum 0 or 1,
+    //are diffuse. Everything else is a principled BSDF, metallic with the Ks colour when Kd is black.
+    //Of the texture maps only map_Kd and map_Bump are used, read from PNG, JPEG, PFM or Radiance HDR
+    //files looked up relative to the directory of the .obj file.
+    pub fn new(material: &tobj::Material, directory: &Path) -> MtlMaterial {
+        let color = |value: [f32; 3]| {
+            Spectrum::new(fp::from(value[0]), fp::from(value[1]), fp::from(value[2]))
+        };
+        let diffuse: Spectrum = material
+            .diffuse
+            .map(color)
+            .unwrap_or(Spectrum::from(DEFAULT_ALBEDO));
+        let specular: Spectrum = material.specular.map(color).unwrap_or_default();
+        //Exporters tend to write a placeholder Kd next to map_Kd, so the texture replaces it
+        let diffuse_texture: Option<Arc<dyn Texture>> = material
+            .diffuse_texture
+            .as_deref()
+            .and_then(|statement| load_texture(statement, directory, true));
+        let roughness: Option<fp> = material.shininess.map(phong_to_roughness);
+        let ior: fp = material
+            .optical_density
+            .map(fp::from)
+            .filter(|&ior| ior > 0.0)
+            .unwrap_or(1.5);
+        let transparent: bool = material.dissolve.is_some_and(|dissolve| dissolve < 1.0)
+            || matches!(material.illumination_model, Some(4 | 6 | 7 | 9));
+
+        let white: Arc<dyn Texture> = Arc::new(ConstantTexture::new(Spectrum::from(1.0)));
+        let bsdf: Arc<dyn Bsdf> = if transparent {
+            //Glass without Ns is smooth
+            let roughness: fp = roughness.unwrap_or(0.0);
+            if roughness > 0.0 {
+                let alpha: fp = roughness * roughness;
+                Arc::new(RoughDielectric::new(
+                    white,
+                    ior,
+                    MicrofacetDistribution::Ggx {
+                        alpha_x: alpha,
+                        alpha_y: alpha,
+                    },
+                ))
+            } else {
+                Arc::new(Dielectric::new(white, ior))
+            }
+        } else if specular.is_black() || matches!(material.illumination_model, Some(0 | 1)) {
+            Arc::new(Lambert::new(
+                diffuse_texture.unwrap_or_else(|| Arc::new(ConstantTexture::new(diffuse))),
+            ))
+        } else {
+            let metallic: bool = diffuse.is_black() && diffuse_texture.is_none();
+            let base_color: Arc<dyn Texture> = match diffuse_texture {
+                Some(texture) => texture,
+                None if metallic => Arc::new(ConstantTexture::new(specular)),
+                None => Arc::new(ConstantTexture::new(diffuse)),
+            };
+            Arc::new(PrincipledBsdf::new(PrincipledParameters {
+                base_color,
+                metallic: if metallic { 1.0 } else { 0.0 },
+                //A missing Ns is the exponent 0 of the .mtl format, the roughest lobe
+                roughness: roughness.unwrap_or(1.0),
+                //A Ks of 0.5, the usual default, gives the 4% reflectance of common dielectrics
+                specular: specular.luminance().clamp(0.0, 1.0),
+                specular_tint: 0.0,
+                sheen: 0.0,
+                sheen_tint: 0.5,
+                clearcoat: 0.0,
+                clearcoat_gloss: 1.0,
+                transmission: 0.0,
+                anisotropic: 0.0,
+                ior,
+            }))
+        };
+
+        let emission: Option<Arc<dyn Texture>> = material
+            .unknown_param
+            .get("Ke")
+            .and_then(|value| parse_color(&material.name, "Ke", value))
+            .filter(|emission| !emission.is_black())
+            .map(|emission| Arc::new(ConstantTexture::new(emission)) as Arc<dyn Texture>);
+
+        let bump_map: Option<Arc<BumpMap>> =
+            material.normal_texture.as_deref().and_then(|statement| {
+                load_texture(statement, directory, false).map(|height| {
+                    Arc::new(BumpMap::new(
+                        height,
+                        texture_option(statement, "-bm").unwrap_or(1.0),
+                    ))
+                })
+            });
+
+        MtlMaterial {
+            bsdf,
+            emission,
+            bump_map,
+        }
+    }
+}
+
+//Phong exponent Ns to principled roughness through the Beckmann width of Walter et al. 2007,
+//alpha = sqrt(2 / (Ns + 2)). Ns at the top of its 0 to 1000 range is taken as perfectly smooth.
+fn phong_to_roughness(shininess: f32) -> fp {
+    let shininess = fp::from(shininess).max(0.0);
+    if shininess >= 1000.0 {
+        return 0.0;
+    }
+    (2.0 / (shininess + 2.0)).sqrt().sqrt()
+}
+
+//Colours are three numbers, or a single one for grey
+fn parse_color(material_name: &str, key: &str, value: &str) -> Option<Spectrum> {
+    let values: Vec<fp> = value
+        .split_whitespace()
+        .map(|token| token.parse::<fp>())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    match values[..] {
+        [grey] => Some(Spectrum::from(grey)),
+        [r, g, b] => Some(Spectrum::new(r, g, b)),
+        _ => {
+            warn!(
+                "Warning: found invalid {} \"{}\" in material {}, ignoring it...",
+                key, value, material_name
+            );
+            None
+        }
+    }
+}
+
+//Texture statements may start with options such as "-bm 0.5", the file name comes last
+fn texture_file(statement: &str, directory: &Path) -> Option<PathBuf> {
+    statement
+        .split_whitespace()
+        .last()
+        .map(|name| directory.join(name.replace('\\', "/")))
+}
+
+fn texture_option(statement: &str, option: &str) -> Option<fp> {
+    let tokens: Vec<&str> = statement.split_whitespace().collect();
+    tokens
+        .windows(2)
+        .find(|pair| pair[0] == option)
+        .and_then(|pair| pair[1].parse().ok())
+}
+
+//Textures that cannot be read are left out with a warning rather than failing the scene, as .mtl
+//files often refer to images that were not shipped along
+fn load_texture(statement: &str, directory: &Path, srgb: bool) -> Option<Arc<dyn Texture>> {
+    let file = texture_file(statement, directory)?;
+    match read_image(&file, srgb) {
+        Ok(image) => Some(Arc::new(BitmapTexture::new(Arc::new(image)))),
+        Err(error) => {
+            warn!(
+                "Warning: could not load texture {}: {}, ignoring it...",
+                file.display(),
+                error
+            );
+            None
+        }
+    }
+}
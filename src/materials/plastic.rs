@@ -0,0 +1,133 @@
+use crate::common::*;
+use crate::materials::fresnel::{diffuse_fresnel, fresnel_dielectric};
+use crate::materials::{Bsdf, BsdfSample};
+use crate::textures::Texture;
+use crate::utilities::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use std::f64::consts::FRAC_1_PI;
+use std::sync::Arc;
+
+//Diffuse base under a smooth dielectric coat, as in Tungsten's plastic. Light reflected back down
+//by the inside of the coat hits the base again, which is accounted for analytically.
+#[derive(Debug, Clone)]
+pub struct Plastic {
+    substrate: PlasticSubstrate,
+}
+
+impl Plastic {
+    pub fn new(albedo: Arc<dyn Texture>, ior: fp, thickness: fp, sigma_a: Spectrum) -> Plastic {
+        Plastic {
+            substrate: PlasticSubstrate::new(albedo, ior, thickness, sigma_a),
+        }
+    }
+}
+
+//The diffuse part shared by smooth and rough plastic
+#[derive(Debug, Clone)]
+pub(crate) struct PlasticSubstrate {
+    albedo: Arc<dyn Texture>,
+    pub ior: fp,
+    //Absorption of the coat over its thickness, for a ray crossing it straight down
+    optical_depth: Spectrum,
+    //Fraction of light left after crossing the coat down and up again
+    average_transmittance: fp,
+    //Reflectance of the inside of the coat for diffuse light
+    diffuse_fresnel: fp,
+}
+
+impl PlasticSubstrate {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        ior: fp,
+        thickness: fp,
+        sigma_a: Spectrum,
+    ) -> PlasticSubstrate {
+        let optical_depth: Spectrum = sigma_a * thickness;
+        let average_optical_depth: fp = (optical_depth.x + optical_depth.y + optical_depth.z) / 3.0;
+        PlasticSubstrate {
+            albedo,
+            ior,
+            optical_depth,
+            average_transmittance: (-2.0 * average_optical_depth).exp(),
+            diffuse_fresnel: diffuse_fresnel(ior),
+        }
+    }
+
+    //Probability of sampling the coat rather than the base, in proportion to how much light each
+    //returns
+    pub fn specular_probability(&self, wo: Vec3) -> fp {
+        let specular_weight: fp = fresnel_dielectric(wo.z, self.ior);
+        let substrate_weight: fp = self.average_transmittance * (1.0 - specular_weight);
+        if specular_weight + substrate_weight <= 0.0 {
+            return 0.0;
+        }
+        specular_weight / (specular_weight + substrate_weight)
+    }
+
+    //Light passing the coat, bouncing between the base and the coat any number of times and
+    //leaving through the coat again. Directions have to be above the surface.
+    pub fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        let eta: fp = 1.0 / self.ior;
+        let fresnel_i: fp = fresnel_dielectric(wo.z, self.ior);
+        let fresnel_o: fp = fresnel_dielectric(wi.z, self.ior);
+        let albedo: Spectrum = self.albedo.evaluate(uv);
+        let mut f: Spectrum = albedo / (Spectrum::from(1.0) - albedo * self.diffuse_fresnel)
+            * ((1.0 - fresnel_i) * (1.0 - fresnel_o) * eta * eta * FRAC_1_PI);
+        if !self.optical_depth.is_black() {
+            let path: fp = 1.0 / wi.z + 1.0 / wo.z;
+            f *= Spectrum::new(
+                (-self.optical_depth.x * path).exp(),
+                (-self.optical_depth.y * path).exp(),
+                (-self.optical_depth.z * path).exp(),
+            );
+        }
+        f
+    }
+
+    pub fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv)
+    }
+}
+
+impl Bsdf for Plastic {
+    //Only the diffuse part, the coat reflects in a delta lobe
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Spectrum::default();
+        }
+        self.substrate.eval(wo, wi, uv)
+    }
+
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, u: Point2) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let specular_probability: fp = self.substrate.specular_probability(wo);
+        if u_component < specular_probability {
+            let wi: Vec3 = Vec3::new(-wo.x, -wo.y, wo.z);
+            return Some(BsdfSample {
+                wi,
+                f: Spectrum::from(fresnel_dielectric(wo.z, self.substrate.ior) / wi.z),
+                pdf: specular_probability,
+                is_specular: true,
+            });
+        }
+        let wi: Vec3 = cosine_sample_hemisphere(u);
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf: self.pdf(wo, wi, uv),
+            is_specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, _uv: Point2) -> fp {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        cosine_hemisphere_pdf(wi.z) * (1.0 - self.substrate.specular_probability(wo))
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.substrate.albedo(uv)
+    }
+}
@@ -0,0 +1,291 @@
+use crate::common::*;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::roughdielectric::RoughDielectric;
+use crate::materials::{reflect, Bsdf, BsdfSample};
+use crate::textures::Texture;
+use crate::utilities::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use std::f64::consts::{FRAC_1_PI, PI};
+use std::sync::Arc;
+
+//Parameters of the principled BSDF, all but the base color in [0, 1]
+#[derive(Debug, Clone)]
+pub struct PrincipledParameters {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: fp,
+    pub roughness: fp,
+    //Reflectance of the dielectric specular lobe at normal incidence, 0.5 is 4%
+    pub specular: fp,
+    //Tints the dielectric specular lobe towards the base color
+    pub specular_tint: fp,
+    //Extra retro-reflection at grazing angles, as seen on cloth
+    pub sheen: fp,
+    pub sheen_tint: fp,
+    //Strength of a second, clear specular layer on top
+    pub clearcoat: fp,
+    pub clearcoat_gloss: fp,
+    //Fraction of the dielectric part that lets light through instead of scattering diffusely
+    pub transmission: fp,
+    //Stretches the specular lobe along the surface tangent, the direction of increasing u
+    pub anisotropic: fp,
+    pub ior: fp,
+}
+
+//Burley's principled BSDF as presented by Disney in 2012 and 2015, without subsurface scattering.
+//Diffuse, sheen, specular, clearcoat and transmission lobes are mixed by the parameters and each
+//is sampled in proportion to an estimate of how much light it reflects.
+#[derive(Debug, Clone)]
+pub struct PrincipledBsdf {
+    parameters: PrincipledParameters,
+    specular_distribution: MicrofacetDistribution,
+    clearcoat_alpha: fp,
+    //Rough glass tinted by the base color, used as is for the transmission lobe
+    transmission_lobe: RoughDielectric,
+}
+
+//Lobe weights and sampling probabilities at a given point and outgoing direction
+struct Lobes {
+    base_color: Spectrum,
+    diffuse_weight: fp,
+    specular_weight: fp,
+    transmission_weight: fp,
+    //Probabilities of sampling diffuse, specular, clearcoat and transmission, in that order
+    probabilities: [fp; 4],
+}
+
+fn schlick_weight(cos_theta: fp) -> fp {
+    let m: fp = (1.0 - cos_theta).clamp(0.0, 1.0);
+    (m * m) * (m * m) * m
+}
+
+fn schlick_fresnel(f0: Spectrum, cos_theta: fp) -> Spectrum {
+    f0 + (Spectrum::from(1.0) - f0) * schlick_weight(cos_theta)
+}
+
+fn lerp(t: fp, a: Spectrum, b: Spectrum) -> Spectrum {
+    a * (1.0 - t) + b * t
+}
+
+//Hue of a color with its luminance taken out
+fn tint(base_color: Spectrum) -> Spectrum {
+    let luminance: fp = base_color.luminance();
+    if luminance > 0.0 {
+        base_color / luminance
+    } else {
+        Spectrum::from(1.0)
+    }
+}
+
+//Berry's distribution, GTR with gamma = 1, used for the clearcoat
+fn gtr1(cos_theta_h: fp, alpha: fp) -> fp {
+    let alpha2: fp = alpha * alpha;
+    (alpha2 - 1.0) / (PI * alpha2.ln() * (1.0 + (alpha2 - 1.0) * cos_theta_h * cos_theta_h))
+}
+
+//Smith masking for isotropic GGX, as used by the clearcoat with a fixed roughness
+fn smith_g1_ggx(cos_theta: fp, alpha: fp) -> fp {
+    let alpha2: fp = alpha * alpha;
+    let cos2_theta: fp = cos_theta * cos_theta;
+    2.0 * cos_theta / (cos_theta + (alpha2 + cos2_theta - alpha2 * cos2_theta).sqrt())
+}
+
+impl PrincipledBsdf {
+    pub fn new(parameters: PrincipledParameters) -> PrincipledBsdf {
+        let alpha: fp = fp::max(0.001, parameters.roughness * parameters.roughness);
+        let aspect: fp = (1.0 - 0.9 * parameters.anisotropic).sqrt();
+        let specular_distribution = MicrofacetDistribution::Ggx {
+            alpha_x: fp::max(0.001, alpha / aspect),
+            alpha_y: fp::max(0.001, alpha * aspect),
+        };
+        let clearcoat_alpha: fp = 0.1 + (0.001 - 0.1) * parameters.clearcoat_gloss;
+        let transmission_lobe = RoughDielectric::new(
+            parameters.base_color.clone(),
+            parameters.ior,
+            MicrofacetDistribution::Ggx {
+                alpha_x: alpha,
+                alpha_y: alpha,
+            },
+        );
+        PrincipledBsdf {
+            parameters,
+            specular_distribution,
+            clearcoat_alpha,
+            transmission_lobe,
+        }
+    }
+
+    fn lobes(&self, wo: Vec3, uv: Point2) -> Lobes {
+        let p = &self.parameters;
+        let base_color: Spectrum = p.base_color.evaluate(uv);
+        let dielectric: fp = 1.0 - p.metallic;
+        let diffuse_weight: fp = dielectric * (1.0 - p.transmission);
+        let transmission_weight: fp = dielectric * p.transmission;
+        let specular_weight: fp = 1.0 - transmission_weight;
+
+        //Only light arriving from outside can reach the opaque lobes
+        let probabilities: [fp; 4] = if wo.z > 0.0 {
+            let fresnel: fp = schlick_weight(wo.z);
+            let specular_f0: fp = self.specular_f0(base_color).luminance();
+            [
+                diffuse_weight * base_color.luminance(),
+                specular_weight * (specular_f0 + (1.0 - specular_f0) * fresnel),
+                0.25 * p.clearcoat * (0.04 + 0.96 * fresnel),
+                transmission_weight,
+            ]
+        } else {
+            [0.0, 0.0, 0.0, transmission_weight]
+        };
+        let total: fp = probabilities.iter().sum();
+        Lobes {
+            base_color,
+            diffuse_weight,
+            specular_weight,
+            transmission_weight,
+            probabilities: if total > 0.0 {
+                probabilities.map(|probability| probability / total)
+            } else {
+                [0.0; 4]
+            },
+        }
+    }
+
+    //Reflectance of the specular lobe at normal incidence, from dielectric to the base color of
+    //metals
+    fn specular_f0(&self, base_color: Spectrum) -> Spectrum {
+        let p = &self.parameters;
+        let dielectric_f0: Spectrum =
+            lerp(p.specular_tint, Spectrum::from(1.0), tint(base_color)) * (0.08 * p.specular);
+        lerp(p.metallic, dielectric_f0, base_color)
+    }
+
+    //Diffuse, sheen, specular and clearcoat, for directions both above the surface
+    fn eval_reflection(&self, lobes: &Lobes, wo: Vec3, wi: Vec3) -> Spectrum {
+        let p = &self.parameters;
+        let wh: Vec3 = (wo + wi).normalize();
+        let cos_theta_d: fp = wi.dot(wh);
+        let mut f: Spectrum = Spectrum::default();
+
+        if lobes.diffuse_weight > 0.0 {
+            //Burley diffuse with its retro-reflection at grazing angles
+            let fresnel_d90: fp = 0.5 + 2.0 * p.roughness * cos_theta_d * cos_theta_d;
+            let retro = |cos_theta: fp| 1.0 + (fresnel_d90 - 1.0) * schlick_weight(cos_theta);
+            let diffuse: Spectrum = lobes.base_color * (FRAC_1_PI * retro(wo.z) * retro(wi.z));
+            let sheen: Spectrum = lerp(p.sheen_tint, Spectrum::from(1.0), tint(lobes.base_color))
+                * (p.sheen * schlick_weight(cos_theta_d));
+            f += (diffuse + sheen) * lobes.diffuse_weight;
+        }
+
+        if lobes.specular_weight > 0.0 {
+            let fresnel: Spectrum =
+                schlick_fresnel(self.specular_f0(lobes.base_color), cos_theta_d);
+            let distribution = &self.specular_distribution;
+            f += fresnel
+                * (lobes.specular_weight * distribution.d(wh) * distribution.g(wo, wi)
+                    / (4.0 * wo.z * wi.z));
+        }
+
+        if p.clearcoat > 0.0 {
+            let fresnel: fp = 0.04 + 0.96 * schlick_weight(cos_theta_d);
+            let masking: fp = smith_g1_ggx(wo.z, 0.25) * smith_g1_ggx(wi.z, 0.25);
+            f += Spectrum::from(
+                0.25 * p.clearcoat * fresnel * gtr1(wh.z, self.clearcoat_alpha) * masking
+                    / (4.0 * wo.z * wi.z),
+            );
+        }
+        f
+    }
+
+    //Solid angle densities of sampling wi with the specular and the clearcoat lobe
+    fn reflection_pdfs(&self, wo: Vec3, wi: Vec3) -> (fp, fp) {
+        let wh: Vec3 = (wo + wi).normalize();
+        let jacobian: fp = 1.0 / (4.0 * wo.dot(wh));
+        (
+            self.specular_distribution.visible_normal_pdf(wo, wh) * jacobian,
+            gtr1(wh.z, self.clearcoat_alpha) * wh.z * jacobian,
+        )
+    }
+
+    //Microfacet normal of the clearcoat, sampled in proportion to D(wh) cos(theta_h)
+    fn sample_clearcoat_normal(&self, u: Point2) -> Vec3 {
+        let alpha2: fp = self.clearcoat_alpha * self.clearcoat_alpha;
+        let cos_theta: fp = ((1.0 - alpha2.powf(1.0 - u.x)) / (1.0 - alpha2))
+            .clamp(0.0, 1.0)
+            .sqrt();
+        let sin_theta: fp = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * PI * u.y).sin_cos();
+        Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+    }
+}
+
+impl Bsdf for PrincipledBsdf {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        let lobes: Lobes = self.lobes(wo, uv);
+        let mut f: Spectrum = Spectrum::default();
+        if wo.z > 0.0 && wi.z > 0.0 {
+            f += self.eval_reflection(&lobes, wo, wi);
+        }
+        if lobes.transmission_weight > 0.0 {
+            f += self.transmission_lobe.eval(wo, wi, uv) * lobes.transmission_weight;
+        }
+        f
+    }
+
+    //u_component picks the lobe, what is left of it is handed on to the transmission lobe which
+    //picks between reflection and refraction
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, u: Point2) -> Option<BsdfSample> {
+        let lobes: Lobes = self.lobes(wo, uv);
+        let [diffuse, specular, clearcoat, _] = lobes.probabilities;
+        let wi: Vec3 = if u_component < diffuse + specular + clearcoat {
+            let wi: Vec3 = if u_component < diffuse {
+                cosine_sample_hemisphere(u)
+            } else if u_component < diffuse + specular {
+                reflect(wo, self.specular_distribution.sample_visible_normal(wo, u))
+            } else {
+                reflect(wo, self.sample_clearcoat_normal(u))
+            };
+            //The opaque lobes are only counted in the pdf above the surface
+            if wi.z <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let offset: fp = diffuse + specular + clearcoat;
+            let u_remapped: fp = ((u_component - offset) / (1.0 - offset)).min(1.0 - fp::EPSILON);
+            self.transmission_lobe.sample(wo, uv, u_remapped, u)?.wi
+        };
+        let pdf: fp = self.pdf(wo, wi, uv);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, uv: Point2) -> fp {
+        let lobes: Lobes = self.lobes(wo, uv);
+        let [diffuse, specular, clearcoat, transmission] = lobes.probabilities;
+        let mut pdf: fp = 0.0;
+        if wo.z > 0.0 && wi.z > 0.0 {
+            let (specular_pdf, clearcoat_pdf) = self.reflection_pdfs(wo, wi);
+            pdf += diffuse * cosine_hemisphere_pdf(wi.z)
+                + specular * specular_pdf
+                + clearcoat * clearcoat_pdf;
+        }
+        if transmission > 0.0 {
+            pdf += transmission * self.transmission_lobe.pdf(wo, wi, uv);
+        }
+        pdf
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.parameters.base_color.evaluate(uv)
+    }
+
+    //Transmissive materials need to know which side they are seen from
+    fn has_transmission(&self) -> bool {
+        self.parameters.metallic < 1.0 && self.parameters.transmission > 0.0
+    }
+}
@@ -0,0 +1,85 @@
+use crate::common::*;
+use crate::materials::fresnel::fresnel_conductor;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::{reflect, Bsdf, BsdfSample};
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Metal with a microfacet surface, the Cook-Torrance model with a GGX or Beckmann distribution of
+//normals
+#[derive(Debug, Clone)]
+pub struct RoughConductor {
+    //Extra tint on top of the Fresnel reflectance
+    albedo: Arc<dyn Texture>,
+    eta: Spectrum,
+    k: Spectrum,
+    distribution: MicrofacetDistribution,
+}
+
+impl RoughConductor {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        eta: Spectrum,
+        k: Spectrum,
+        distribution: MicrofacetDistribution,
+    ) -> RoughConductor {
+        RoughConductor {
+            albedo,
+            eta,
+            k,
+            distribution,
+        }
+    }
+}
+
+impl Bsdf for RoughConductor {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Spectrum::default();
+        }
+        let wh: Vec3 = wo + wi;
+        if wh.is_black() {
+            return Spectrum::default();
+        }
+        let wh: Vec3 = wh.normalize();
+        let fresnel: Spectrum = fresnel_conductor(wo.dot(wh), self.eta, self.k);
+        self.albedo.evaluate(uv)
+            * fresnel
+            * (self.distribution.d(wh) * self.distribution.g(wo, wi) / (4.0 * wo.z * wi.z))
+    }
+
+    //Only microfacet normals visible from wo are sampled
+    fn sample(&self, wo: Vec3, uv: Point2, _u_component: fp, u: Point2) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wh: Vec3 = self.distribution.sample_visible_normal(wo, u);
+        let wi: Vec3 = reflect(wo, wh);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf: self.pdf(wo, wi, uv),
+            is_specular: false,
+        })
+    }
+
+    //Density of the visible normal, times the Jacobian of the reflection 1 / (4 wo.wh)
+    fn pdf(&self, wo: Vec3, wi: Vec3, _uv: Point2) -> fp {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wh: Vec3 = wo + wi;
+        if wh.is_black() {
+            return 0.0;
+        }
+        let wh: Vec3 = wh.normalize();
+        self.distribution.visible_normal_pdf(wo, wh) / (4.0 * wo.dot(wh))
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv) * fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::common::*;
+use crate::materials::fresnel::fresnel_dielectric;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::{reflect, refract, Bsdf, BsdfSample};
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Rough boundary of a transparent medium, Walter et al.'s microfacet model for reflection and
+//refraction through rough surfaces. Only single scattering is modelled, so rough glass loses energy.
+#[derive(Debug, Clone)]
+pub struct RoughDielectric {
+    //Tint applied to both reflection and refraction
+    albedo: Arc<dyn Texture>,
+    //IOR inside over IOR outside
+    ior: fp,
+    distribution: MicrofacetDistribution,
+}
+
+impl RoughDielectric {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        ior: fp,
+        distribution: MicrofacetDistribution,
+    ) -> RoughDielectric {
+        RoughDielectric {
+            albedo,
+            ior,
+            distribution,
+        }
+    }
+
+    //IOR on the side of wo over IOR on the other side
+    fn relative_eta(&self, wo: Vec3) -> fp {
+        if wo.z > 0.0 {
+            1.0 / self.ior
+        } else {
+            self.ior
+        }
+    }
+
+    //Microfacet normal taking wo to wi, facing outside. None when no microfacet can.
+    fn half_vector(&self, wo: Vec3, wi: Vec3) -> Option<Vec3> {
+        let reflection: bool = wo.z * wi.z > 0.0;
+        let wh: Vec3 = if reflection {
+            wo + wi
+        } else {
+            wo * self.relative_eta(wo) + wi
+        };
+        if wh.is_black() {
+            return None;
+        }
+        let wh: Vec3 = wh.normalize();
+        let wh: Vec3 = if wh.z < 0.0 { -wh } else { wh };
+        //Both directions have to see the microfacet from the same side they see the surface from
+        if wo.dot(wh) * wo.z <= 0.0 || wi.dot(wh) * wi.z <= 0.0 {
+            return None;
+        }
+        Some(wh)
+    }
+
+    //Density of normals visible from wo, from either side of the surface
+    fn visible_normal_pdf(&self, wo: Vec3, wh: Vec3) -> fp {
+        self.distribution.g1(wo) * wo.dot(wh).abs() * self.distribution.d(wh) / wo.z.abs()
+    }
+}
+
+impl Bsdf for RoughDielectric {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return Spectrum::default();
+        }
+        let wh: Vec3 = match self.half_vector(wo, wi) {
+            Some(wh) => wh,
+            None => return Spectrum::default(),
+        };
+        let fresnel: fp = fresnel_dielectric(wo.dot(wh), self.ior);
+        let d_g: fp = self.distribution.d(wh) * self.distribution.g(wo, wi);
+        if wo.z * wi.z > 0.0 {
+            return self.albedo.evaluate(uv) * (fresnel * d_g / (4.0 * wo.z.abs() * wi.z.abs()));
+        }
+        //The (eta_o / eta_i)^2 scaling of radiance cancels the eta^2 of the Jacobian
+        let eta: fp = self.relative_eta(wo);
+        let denominator: fp = eta * wo.dot(wh) + wi.dot(wh);
+        self.albedo.evaluate(uv)
+            * ((1.0 - fresnel) * d_g * wo.dot(wh).abs() * wi.dot(wh).abs() * eta * eta
+                / (denominator * denominator * wo.z.abs() * wi.z.abs()))
+    }
+
+    //A visible normal is sampled, then u_component picks reflection off it with the probability of
+    //the Fresnel reflectance
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, u: Point2) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let side: fp = wo.z.signum();
+        let wh: Vec3 = self.distribution.sample_visible_normal(wo * side, u) * side;
+        let fresnel: fp = fresnel_dielectric(wo.dot(wh) * side, self.ior);
+        let reflection: bool = u_component < fresnel;
+        let wi: Vec3 = if reflection {
+            reflect(wo, wh)
+        } else {
+            refract(wo, wh, self.relative_eta(wo))?
+        };
+        //Steep microfacets can send wi to the other side of the macro surface than the lobe would,
+        //such samples belong to the other lobe and would get its pdf
+        if (wo.z * wi.z > 0.0) != reflection {
+            return None;
+        }
+        let pdf: fp = self.pdf(wo, wi, uv);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, _uv: Point2) -> fp {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+        let wh: Vec3 = match self.half_vector(wo, wi) {
+            Some(wh) => wh,
+            None => return 0.0,
+        };
+        let fresnel: fp = fresnel_dielectric(wo.dot(wh), self.ior);
+        let normal_pdf: fp = self.visible_normal_pdf(wo, wh);
+        if wo.z * wi.z > 0.0 {
+            return normal_pdf * fresnel / (4.0 * wo.dot(wh).abs());
+        }
+        let eta: fp = self.relative_eta(wo);
+        let denominator: fp = eta * wo.dot(wh) + wi.dot(wh);
+        normal_pdf * (1.0 - fresnel) * wi.dot(wh).abs() / (denominator * denominator)
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv)
+    }
+
+    fn has_transmission(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textures::constanttexture::ConstantTexture;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::PI;
+
+    const THETA_STRATA: usize = 100;
+    const PHI_STRATA: usize = 100;
+    const SAMPLES: usize = THETA_STRATA * PHI_STRATA;
+    //Cosines of wo, negative from inside the glass
+    const COS_THETA_O: [fp; 6] = [1.0, 0.5, 0.1, -1.0, -0.5, -0.1];
+
+    fn glass(distribution: MicrofacetDistribution) -> RoughDielectric {
+        RoughDielectric::new(
+            Arc::new(ConstantTexture::new(Spectrum::from(1.0))),
+            1.5,
+            distribution,
+        )
+    }
+
+    struct Estimates {
+        //Mean of f |cos| / pdf over samples, failed ones counting as 0
+        sampled: fp,
+        //Integral of eval |cos| over the sphere
+        integrated: fp,
+        //Fraction of samples that succeeded
+        sampled_fraction: fp,
+        //Integral of pdf over the sphere
+        integrated_pdf: fp,
+        //Fraction of the incident energy scattered, the sampled weights without the eta^2
+        //scaling of refracted radiance
+        energy: fp,
+    }
+
+    //The integrals are stratified estimates over the sphere, independent of the BSDF's own sampling
+    fn estimate(bsdf: &RoughDielectric, cos_theta_o: fp, seed: u64) -> Estimates {
+        let wo: Vec3 = Vec3::new((1.0 - cos_theta_o * cos_theta_o).sqrt(), 0.0, cos_theta_o);
+        let uv: Point2 = Point2::new(0.0, 0.0);
+        let mut rng: StdRng = StdRng::seed_from_u64(seed);
+        let mut estimates = Estimates {
+            sampled: 0.0,
+            integrated: 0.0,
+            sampled_fraction: 0.0,
+            integrated_pdf: 0.0,
+            energy: 0.0,
+        };
+        for stratum in 0..SAMPLES {
+            let u: Point2 = Point2::new(rng.gen(), rng.gen());
+            if let Some(sample) = bsdf.sample(wo, uv, rng.gen(), u) {
+                let weight: fp = sample.f.x * sample.wi.z.abs() / sample.pdf;
+                let eta: fp = bsdf.relative_eta(wo);
+                estimates.sampled += weight;
+                estimates.sampled_fraction += 1.0;
+                estimates.energy += if wo.z * sample.wi.z > 0.0 {
+                    weight
+                } else {
+                    weight / (eta * eta)
+                };
+            }
+
+            //Jittered strata of the sphere, uniform in theta to resolve lobes around the poles
+            let theta: fp =
+                PI * ((stratum / PHI_STRATA) as fp + rng.gen::<fp>()) / THETA_STRATA as fp;
+            let phi: fp =
+                2.0 * PI * ((stratum % PHI_STRATA) as fp + rng.gen::<fp>()) / PHI_STRATA as fp;
+            let wi: Vec3 = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            );
+            let solid_angle: fp = 2.0 * PI * PI * theta.sin();
+            estimates.integrated += bsdf.eval(wo, wi, uv).x * wi.z.abs() * solid_angle;
+            estimates.integrated_pdf += bsdf.pdf(wo, wi, uv) * solid_angle;
+        }
+        let n: fp = SAMPLES as fp;
+        Estimates {
+            sampled: estimates.sampled / n,
+            integrated: estimates.integrated / n,
+            sampled_fraction: estimates.sampled_fraction / n,
+            integrated_pdf: estimates.integrated_pdf / n,
+            energy: estimates.energy / n,
+        }
+    }
+
+    fn check_sampling_matches_eval(distribution: MicrofacetDistribution) {
+        let bsdf: RoughDielectric = glass(distribution);
+        for (seed, cos_theta_o) in COS_THETA_O.into_iter().enumerate() {
+            let estimates: Estimates = estimate(&bsdf, cos_theta_o, seed as u64);
+            assert!(
+                (estimates.sampled - estimates.integrated).abs() < 0.02 * estimates.integrated,
+                "{:?} at cos {}: sampled {} integrated {}",
+                distribution,
+                cos_theta_o,
+                estimates.sampled,
+                estimates.integrated
+            );
+            assert!(
+                (estimates.sampled_fraction - estimates.integrated_pdf).abs() < 0.02,
+                "{:?} at cos {}: {} of samples succeeded, pdf integrates to {}",
+                distribution,
+                cos_theta_o,
+                estimates.sampled_fraction,
+                estimates.integrated_pdf
+            );
+        }
+    }
+
+    #[test]
+    fn ggx_sampling_matches_eval() {
+        check_sampling_matches_eval(MicrofacetDistribution::Ggx {
+            alpha_x: 0.3,
+            alpha_y: 0.3,
+        });
+    }
+
+    #[test]
+    fn beckmann_sampling_matches_eval() {
+        check_sampling_matches_eval(MicrofacetDistribution::Beckmann { alpha: 0.3 });
+    }
+
+    //Light bouncing between microfacets is lost, never created. At an alpha of 0.3 GGX's long tail
+    //of steep microfacets past the critical angle loses 10 to 22% of the light arriving from inside,
+    //a tenth even at normal incidence, and Beckmann at most 12%. Compounded over the internal
+    //bounces, a glass sphere in a white furnace averages about 0.78 with GGX and 0.92 with Beckmann.
+    #[test]
+    fn single_scattering_loses_energy() {
+        let ggx: RoughDielectric = glass(MicrofacetDistribution::Ggx {
+            alpha_x: 0.3,
+            alpha_y: 0.3,
+        });
+        let beckmann: RoughDielectric = glass(MicrofacetDistribution::Beckmann { alpha: 0.3 });
+        for (seed, cos_theta_o) in COS_THETA_O.into_iter().enumerate() {
+            let ggx_energy: fp = estimate(&ggx, cos_theta_o, seed as u64).energy;
+            let beckmann_energy: fp = estimate(&beckmann, cos_theta_o, seed as u64).energy;
+            assert!(
+                ggx_energy <= 1.01 && beckmann_energy <= 1.01,
+                "energy created at cos {}: GGX {} Beckmann {}",
+                cos_theta_o,
+                ggx_energy,
+                beckmann_energy
+            );
+            assert!(
+                ggx_energy > 0.7 && beckmann_energy > 0.85,
+                "too much energy lost at cos {}: GGX {} Beckmann {}",
+                cos_theta_o,
+                ggx_energy,
+                beckmann_energy
+            );
+        }
+        let ggx_inside: fp = estimate(&ggx, -1.0, 0).energy;
+        assert!(ggx_inside > 0.85 && ggx_inside < 0.95, "GGX {}", ggx_inside);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::common::*;
+use crate::materials::fresnel::fresnel_dielectric;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::materials::plastic::PlasticSubstrate;
+use crate::materials::{reflect, Bsdf, BsdfSample};
+use crate::textures::Texture;
+use crate::utilities::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use std::sync::Arc;
+
+//Diffuse base under a rough dielectric coat. The coat is a microfacet reflector, the base is lit
+//as if the coat were smooth.
+#[derive(Debug, Clone)]
+pub struct RoughPlastic {
+    substrate: PlasticSubstrate,
+    distribution: MicrofacetDistribution,
+}
+
+impl RoughPlastic {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        ior: fp,
+        thickness: fp,
+        sigma_a: Spectrum,
+        distribution: MicrofacetDistribution,
+    ) -> RoughPlastic {
+        RoughPlastic {
+            substrate: PlasticSubstrate::new(albedo, ior, thickness, sigma_a),
+            distribution,
+        }
+    }
+}
+
+impl Bsdf for RoughPlastic {
+    fn eval(&self, wo: Vec3, wi: Vec3, uv: Point2) -> Spectrum {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Spectrum::default();
+        }
+        let wh: Vec3 = (wo + wi).normalize();
+        let fresnel: fp = fresnel_dielectric(wo.dot(wh), self.substrate.ior);
+        let glossy: fp =
+            fresnel * self.distribution.d(wh) * self.distribution.g(wo, wi) / (4.0 * wo.z * wi.z);
+        self.substrate.eval(wo, wi, uv) + Spectrum::from(glossy)
+    }
+
+    //u_component picks between the coat and the base
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, u: Point2) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wi: Vec3 = if u_component < self.substrate.specular_probability(wo) {
+            reflect(wo, self.distribution.sample_visible_normal(wo, u))
+        } else {
+            cosine_sample_hemisphere(u)
+        };
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, uv),
+            pdf: self.pdf(wo, wi, uv),
+            is_specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, _uv: Point2) -> fp {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wh: Vec3 = (wo + wi).normalize();
+        let glossy_pdf: fp = self.distribution.visible_normal_pdf(wo, wh) / (4.0 * wo.dot(wh));
+        let specular_probability: fp = self.substrate.specular_probability(wo);
+        specular_probability * glossy_pdf
+            + (1.0 - specular_probability) * cosine_hemisphere_pdf(wi.z)
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.substrate.albedo(uv)
+    }
+}
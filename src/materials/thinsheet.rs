@@ -0,0 +1,109 @@
+use crate::common::*;
+use crate::materials::fresnel::{thin_film_reflectance, thin_film_reflectance_interference};
+use crate::materials::{Bsdf, BsdfSample};
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Infinitely thin dielectric layer such as a soap bubble or a window pane. Light is either mirrored
+//or passes straight through, refraction at the two faces cancels out.
+#[derive(Debug, Clone)]
+pub struct ThinSheet {
+    //Tint applied to both reflection and transmission
+    albedo: Arc<dyn Texture>,
+    ior: fp,
+    //Thickness of the layer in micrometres
+    thickness: fp,
+    //Absorption coefficient per micrometre inside the layer
+    sigma_a: Spectrum,
+    enable_interference: bool,
+}
+
+impl ThinSheet {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        ior: fp,
+        thickness: fp,
+        sigma_a: Spectrum,
+        enable_interference: bool,
+    ) -> ThinSheet {
+        ThinSheet {
+            albedo,
+            ior,
+            thickness,
+            sigma_a,
+            enable_interference,
+        }
+    }
+
+    //Reflectance and transmittance of the layer for light arriving at the given angle
+    fn reflectance_transmittance(&self, cos_theta_i: fp) -> (Spectrum, Spectrum) {
+        let (reflectance, cos_theta_t) = if self.enable_interference {
+            thin_film_reflectance_interference(cos_theta_i, self.ior, self.thickness * 1000.0)
+        } else {
+            let (reflectance, cos_theta_t) = thin_film_reflectance(cos_theta_i, self.ior);
+            (Spectrum::from(reflectance), cos_theta_t)
+        };
+        let mut transmittance: Spectrum = Spectrum::from(1.0) - reflectance;
+        if !self.sigma_a.is_black() && cos_theta_t > 0.0 {
+            let optical_depth: Spectrum = self.sigma_a * (self.thickness / cos_theta_t);
+            transmittance *= Spectrum::new(
+                (-optical_depth.x).exp(),
+                (-optical_depth.y).exp(),
+                (-optical_depth.z).exp(),
+            );
+        }
+        (reflectance, transmittance)
+    }
+}
+
+impl Bsdf for ThinSheet {
+    //Both lobes are deltas
+    fn eval(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> Spectrum {
+        Spectrum::default()
+    }
+
+    //u_component picks reflection in proportion to the average reflectance
+    fn sample(&self, wo: Vec3, uv: Point2, u_component: fp, _u: Point2) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let (reflectance, transmittance) = self.reflectance_transmittance(wo.z);
+        let reflected: fp = reflectance.x + reflectance.y + reflectance.z;
+        let transmitted: fp = transmittance.x + transmittance.y + transmittance.z;
+        if reflected + transmitted <= 0.0 {
+            return None;
+        }
+        let reflection_probability: fp = reflected / (reflected + transmitted);
+        let albedo: Spectrum = self.albedo.evaluate(uv);
+        if u_component < reflection_probability {
+            let wi: Vec3 = Vec3::new(-wo.x, -wo.y, wo.z);
+            Some(BsdfSample {
+                wi,
+                f: albedo * reflectance / wi.z.abs(),
+                pdf: reflection_probability,
+                is_specular: true,
+            })
+        } else {
+            let wi: Vec3 = -wo;
+            Some(BsdfSample {
+                wi,
+                f: albedo * transmittance / wi.z.abs(),
+                pdf: 1.0 - reflection_probability,
+                is_specular: true,
+            })
+        }
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3, _uv: Point2) -> fp {
+        0.0
+    }
+
+    fn albedo(&self, uv: Point2) -> Spectrum {
+        self.albedo.evaluate(uv)
+    }
+
+    //Both sides of the sheet are alike, but light does pass through it
+    fn has_transmission(&self) -> bool {
+        true
+    }
+}
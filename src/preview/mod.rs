@@ -0,0 +1,21 @@
+use crate::common::*;
+
+pub mod tevpreview;
+
+//Somewhere to show the image while it is being rendered. Showing the preview is best effort, a
+//sink that fails must not stop the render.
+pub trait PreviewSink: Send + Sync {
+    fn create_image(&self, width: i32, height: i32);
+
+    //RGB values of the pixels in the tile, row by row
+    fn update_tile(&self, tile: &TileBounds, pixels: &[f32]);
+}
+
+//Headless rendering, nothing is shown
+pub struct NoPreview;
+
+impl PreviewSink for NoPreview {
+    fn create_image(&self, _width: i32, _height: i32) {}
+
+    fn update_tile(&self, _tile: &TileBounds, _pixels: &[f32]) {}
+}
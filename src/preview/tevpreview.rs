@@ -0,0 +1,81 @@
+use crate::common::*;
+use crate::preview::PreviewSink;
+use std::io;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tev_client::{PacketCreateImage, PacketUpdateImage, TevClient};
+
+//Default address tev listens on
+pub const TEV_DEFAULT_ADDRESS: &str = "127.0.0.1:14158";
+
+//Streams tiles to an already running tev instance over TCP
+pub struct TevPreview {
+    tev_client: Mutex<TevClient>,
+    image_name: String,
+    //Cleared after the first failed send, so a closed viewer costs one warning and not one per tile
+    connected: AtomicBool,
+}
+
+impl TevPreview {
+    pub fn connect(address: &str, image_name: &str) -> io::Result<TevPreview> {
+        let stream = TcpStream::connect(address)?;
+        Ok(TevPreview {
+            tev_client: Mutex::new(TevClient::wrap(stream)),
+            image_name: image_name.to_string(),
+            connected: AtomicBool::new(true),
+        })
+    }
+
+    fn check_send(&self, result: io::Result<()>) {
+        if let Err(e) = result {
+            if self.connected.swap(false, Ordering::Relaxed) {
+                warn!(
+                    "Warning: lost connection to tev ({}), continuing without preview...",
+                    e
+                );
+            }
+        }
+    }
+}
+
+impl PreviewSink for TevPreview {
+    fn create_image(&self, width: i32, height: i32) {
+        if !self.connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = match self.tev_client.lock() {
+            Ok(mut tev_client) => tev_client.send(PacketCreateImage {
+                image_name: &self.image_name,
+                grab_focus: false,
+                width: width as u32,
+                height: height as u32,
+                channel_names: &["R", "G", "B"],
+            }),
+            Err(_) => return,
+        };
+        self.check_send(result);
+    }
+
+    fn update_tile(&self, tile: &TileBounds, pixels: &[f32]) {
+        if !self.connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = match self.tev_client.lock() {
+            Ok(mut tev_client) => tev_client.send(PacketUpdateImage {
+                image_name: &self.image_name,
+                grab_focus: false,
+                channel_names: &["R", "G", "B"],
+                channel_offsets: &[0, 1, 2],
+                channel_strides: &[3, 3, 3],
+                x: tile.x as u32,
+                y: tile.y as u32,
+                width: tile.width as u32,
+                height: tile.height as u32,
+                data: pixels,
+            }),
+            Err(_) => return,
+        };
+        self.check_send(result);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::common::*;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+//Everything that can go wrong while turning a scene file into a renderable scene. Errors about the
+//contents of the file name the file and the dotted path of the offending key.
+pub enum SceneError {
+    Io {
+        file: PathBuf,
+        source: io::Error,
+    },
+    Toml {
+        file: PathBuf,
+        source: toml::de::Error,
+    },
+    MissingKey {
+        file: PathBuf,
+        key: String,
+    },
+    InvalidValue {
+        file: PathBuf,
+        key: String,
+        expected: &'static str,
+    },
+    Mesh {
+        file: PathBuf,
+        source: tobj::LoadError,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { file, source } => {
+                write!(f, "{}: could not be read: {}", file.display(), source)
+            }
+            SceneError::Toml { file, source } => {
+                write!(f, "{}: not a valid TOML file: {}", file.display(), source)
+            }
+            SceneError::MissingKey { file, key } => {
+                write!(f, "{}: missing required key `{}`", file.display(), key)
+            }
+            SceneError::InvalidValue {
+                file,
+                key,
+                expected,
+            } => write!(
+                f,
+                "{}: invalid value for `{}`, expected {}",
+                file.display(),
+                key,
+                expected
+            ),
+            SceneError::Mesh { file, source } => {
+                write!(f, "{}: could not load mesh: {}", file.display(), source)
+            }
+        }
+    }
+}
+
+//main() reports the errors it returns with Debug, so keep that readable too
+impl fmt::Debug for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Toml { source, .. } => Some(source),
+            SceneError::Mesh { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+//A (possibly missing) value somewhere in a parsed scene file, along with the key path leading to it
+#[derive(Clone)]
+pub struct SceneValue<'a> {
+    file: &'a Path,
+    key: String,
+    value: Option<&'a Value>,
+}
+
+impl<'a> SceneValue<'a> {
+    pub fn root(file: &'a Path, value: &'a Value) -> SceneValue<'a> {
+        SceneValue {
+            file,
+            key: String::new(),
+            value: Some(value),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> SceneValue<'a> {
+        SceneValue {
+            file: self.file,
+            key: if self.key.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", self.key, key)
+            },
+            value: self.value.and_then(|v| v.get(key)),
+        }
+    }
+
+    pub fn at(&self, index: usize) -> SceneValue<'a> {
+        SceneValue {
+            file: self.file,
+            key: format!("{}[{}]", self.key, index),
+            value: self.value.and_then(|v| v.get(index)),
+        }
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.value.is_some()
+    }
+
+    pub fn missing(&self) -> SceneError {
+        SceneError::MissingKey {
+            file: self.file.to_path_buf(),
+            key: self.key.clone(),
+        }
+    }
+
+    pub fn invalid(&self, expected: &'static str) -> SceneError {
+        SceneError::InvalidValue {
+            file: self.file.to_path_buf(),
+            key: self.key.clone(),
+            expected,
+        }
+    }
+
+    fn required(&self) -> Result<&'a Value, SceneError> {
+        self.value.ok_or_else(|| self.missing())
+    }
+
+    //TOML tells integer and float literals apart, scene files use both for the same keys
+    pub fn number(&self) -> Result<fp, SceneError> {
+        match self.required()? {
+            Value::Float(value) => Ok(*value),
+            Value::Integer(value) => Ok(*value as fp),
+            _ => Err(self.invalid("a number")),
+        }
+    }
+
+    pub fn number_or(&self, default: fp) -> Result<fp, SceneError> {
+        if self.is_present() {
+            self.number()
+        } else {
+            Ok(default)
+        }
+    }
+
+    pub fn string(&self) -> Result<&'a str, SceneError> {
+        self.required()?
+            .as_str()
+            .ok_or_else(|| self.invalid("a string"))
+    }
+
+    pub fn optional_string(&self) -> Result<Option<&'a str>, SceneError> {
+        if self.is_present() {
+            self.string().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn boolean_or(&self, default: bool) -> Result<bool, SceneError> {
+        match self.value {
+            Some(value) => value.as_bool().ok_or_else(|| self.invalid("true or false")),
+            None => Ok(default),
+        }
+    }
+
+    pub fn vector3(&self) -> Result<Vector3, SceneError> {
+        match self.required()?.as_array() {
+            Some(components) if components.len() == 3 => Ok(Vector3::new(
+                self.at(0).number()?,
+                self.at(1).number()?,
+                self.at(2).number()?,
+            )),
+            _ => Err(self.invalid("an array of 3 numbers")),
+        }
+    }
+
+    pub fn vector3_or(&self, default: Vector3) -> Result<Vector3, SceneError> {
+        if self.is_present() {
+            self.vector3()
+        } else {
+            Ok(default)
+        }
+    }
+
+    //A file named by a string value, relative paths are relative to the scene file
+    pub fn file_path(&self) -> Result<PathBuf, SceneError> {
+        let file_name = self.string()?;
+        Ok(self
+            .file
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(file_name))
+    }
+
+    //Entries of an array of tables such as [[primitives]], a missing array has no entries
+    pub fn entries(&self) -> Result<Vec<SceneValue<'a>>, SceneError> {
+        match self.value {
+            Some(value) => match value.as_array() {
+                Some(array) => Ok((0..array.len()).map(|i| self.at(i)).collect()),
+                None => Err(self.invalid("an array")),
+            },
+            None => Ok(vec![]),
+        }
+    }
+
+    //The raw TOML value, for callers that accept more than one kind of value under a key
+    pub fn value(&self) -> Option<&'a Value> {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Value {
+        text.parse::<Value>().unwrap()
+    }
+
+    #[test]
+    fn numbers_accept_integers_and_floats() {
+        let toml = parse("a = 2\nb = 2.5\nc = [1, 2.5, -3]");
+        let scene = SceneValue::root(Path::new("scene.toml"), &toml);
+        assert_eq!(scene.get("a").number().unwrap(), 2.0);
+        assert_eq!(scene.get("b").number().unwrap(), 2.5);
+        let c = scene.get("c").vector3().unwrap();
+        assert_eq!([c.x, c.y, c.z], [1.0, 2.5, -3.0]);
+        assert_eq!(scene.get("missing").number_or(4.0).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn missing_keys_name_the_full_path() {
+        let toml = parse("[camera.transform]\nup = [0, 1, 0]");
+        let scene = SceneValue::root(Path::new("scene.toml"), &toml);
+        let position = scene.get("camera").get("transform").get("position");
+        assert!(!position.is_present());
+        let error = position.vector3().unwrap_err();
+        assert!(matches!(error, SceneError::MissingKey { .. }));
+        assert_eq!(
+            error.to_string(),
+            "scene.toml: missing required key `camera.transform.position`"
+        );
+        //Keys below a missing table are missing too, with the path kept
+        let error = scene.get("renderer").get("spp").number().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "scene.toml: missing required key `renderer.spp`"
+        );
+    }
+
+    #[test]
+    fn ill_typed_values_name_the_full_path() {
+        let toml = parse(
+            "[renderer]\nspp = \"many\"\nenable = 1\n\
+             [[primitives]]\ntype = \"sphere\"\n[[primitives]]\ntype = 3\nradius = [1, \"x\", 2]",
+        );
+        let scene = SceneValue::root(Path::new("dir/scene.toml"), &toml);
+        let expect_invalid = |error: SceneError, message: &str| {
+            assert!(matches!(error, SceneError::InvalidValue { .. }));
+            assert_eq!(error.to_string(), message);
+        };
+        expect_invalid(
+            scene.get("renderer").get("spp").number().unwrap_err(),
+            "dir/scene.toml: invalid value for `renderer.spp`, expected a number",
+        );
+        expect_invalid(
+            scene
+                .get("renderer")
+                .get("enable")
+                .boolean_or(true)
+                .unwrap_err(),
+            "dir/scene.toml: invalid value for `renderer.enable`, expected true or false",
+        );
+        let primitives = scene.get("primitives").entries().unwrap();
+        assert_eq!(primitives.len(), 2);
+        assert_eq!(primitives[0].get("type").string().unwrap(), "sphere");
+        expect_invalid(
+            primitives[1].get("type").optional_string().unwrap_err(),
+            "dir/scene.toml: invalid value for `primitives[1].type`, expected a string",
+        );
+        expect_invalid(
+            primitives[1].get("radius").vector3().unwrap_err(),
+            "dir/scene.toml: invalid value for `primitives[1].radius[1]`, expected a number",
+        );
+        expect_invalid(
+            scene.get("renderer").entries().err().unwrap(),
+            "dir/scene.toml: invalid value for `renderer`, expected an array",
+        );
+    }
+
+    #[test]
+    fn file_paths_are_relative_to_the_scene_file() {
+        let toml = parse("file = \"meshes/bunny.obj\"");
+        let scene = SceneValue::root(Path::new("scenes/scene.toml"), &toml);
+        assert_eq!(
+            scene.get("file").file_path().unwrap(),
+            Path::new("scenes/meshes/bunny.obj")
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use crate::common::*;
+use crate::textures::Texture;
+use crate::utilities::imageutils::HdrImage;
+use std::sync::Arc;
+
+//Image mapped onto the unit uv square with v pointing up, repeated outside of it and filtered
+//bilinearly
+#[derive(Debug, Clone)]
+pub struct BitmapTexture {
+    image: Arc<HdrImage>,
+}
+
+impl BitmapTexture {
+    pub fn new(image: Arc<HdrImage>) -> BitmapTexture {
+        BitmapTexture { image }
+    }
+}
+
+impl Texture for BitmapTexture {
+    fn evaluate(&self, uv: Point2) -> Spectrum {
+        let width = self.image.width;
+        let height = self.image.height;
+        //Continuous pixel coordinates with pixel centers at half integers, rows counted from the top
+        let x: fp = (uv.x - uv.x.floor()) * fp::from(width) - 0.5;
+        let y: fp = (1.0 - (uv.y - uv.y.floor())) * fp::from(height) - 0.5;
+        let x0: fp = x.floor();
+        let y0: fp = y.floor();
+        let tx: fp = x - x0;
+        let ty: fp = y - y0;
+        let texel = |xi: fp, yi: fp| -> Spectrum {
+            self.image.get(
+                (xi as i32).rem_euclid(width),
+                (yi as i32).rem_euclid(height),
+            )
+        };
+        texel(x0, y0) * ((1.0 - tx) * (1.0 - ty))
+            + texel(x0 + 1.0, y0) * (tx * (1.0 - ty))
+            + texel(x0, y0 + 1.0) * ((1.0 - tx) * ty)
+            + texel(x0 + 1.0, y0 + 1.0) * (tx * ty)
+    }
+
+    fn average(&self) -> Spectrum {
+        let mut sum: Spectrum = Spectrum::default();
+        for pixel in &self.image.pixels {
+            sum += *pixel;
+        }
+        sum / self.image.pixels.len() as fp
+    }
+}
@@ -0,0 +1,43 @@
+use crate::common::*;
+use crate::textures::Texture;
+use std::sync::Arc;
+
+//Step in uv used for the finite differences of the height
+const BUMP_DELTA: fp = 0.0005;
+
+//Displaces the surface along its normal by a height looked up in a texture, for shading only. The
+//displacement is the mean of the texture's channels times the scale, in scene units.
+#[derive(Debug, Clone)]
+pub struct BumpMap {
+    height: Arc<dyn Texture>,
+    scale: fp,
+}
+
+impl BumpMap {
+    pub fn new(height: Arc<dyn Texture>, scale: fp) -> BumpMap {
+        BumpMap { height, scale }
+    }
+
+    fn displacement(&self, uv: Point2) -> fp {
+        let height: Spectrum = self.height.evaluate(uv);
+        (height.x + height.y + height.z) / 3.0 * self.scale
+    }
+
+    //Normal of the displaced surface given the partial derivatives of the position in u and v,
+    //on the same side as the unperturbed normal. The change of the normal itself is neglected.
+    pub fn shading_normal(&self, uv: Point2, normal: Vec3, dpdu: Vec3, dpdv: Vec3) -> Vec3 {
+        let displacement: fp = self.displacement(uv);
+        let ddisplacement_du: fp =
+            (self.displacement(Point2::new(uv.x + BUMP_DELTA, uv.y)) - displacement) / BUMP_DELTA;
+        let ddisplacement_dv: fp =
+            (self.displacement(Point2::new(uv.x, uv.y + BUMP_DELTA)) - displacement) / BUMP_DELTA;
+        let bumped_dpdu: Vec3 = dpdu + normal * ddisplacement_du;
+        let bumped_dpdv: Vec3 = dpdv + normal * ddisplacement_dv;
+        let mut bumped_normal: Vec3 = bumped_dpdu.cross(bumped_dpdv).normalize();
+        if !bumped_normal.x.is_finite() {
+            return normal;
+        }
+        bumped_normal.face_outward_normal(normal);
+        bumped_normal
+    }
+}
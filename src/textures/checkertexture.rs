@@ -0,0 +1,38 @@
+use crate::common::*;
+use crate::textures::Texture;
+
+//Alternates between two colors on a res_u x res_v grid over the unit uv square
+#[derive(Debug, Clone)]
+pub struct CheckerTexture {
+    on_color: Spectrum,
+    off_color: Spectrum,
+    res_u: fp,
+    res_v: fp,
+}
+
+impl CheckerTexture {
+    pub fn new(on_color: Spectrum, off_color: Spectrum, res_u: fp, res_v: fp) -> CheckerTexture {
+        CheckerTexture {
+            on_color,
+            off_color,
+            res_u,
+            res_v,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn evaluate(&self, uv: Point2) -> Spectrum {
+        let cell_u = (uv.x * self.res_u).floor() as i64;
+        let cell_v = (uv.y * self.res_v).floor() as i64;
+        if (cell_u + cell_v).rem_euclid(2) == 0 {
+            self.on_color
+        } else {
+            self.off_color
+        }
+    }
+
+    fn average(&self) -> Spectrum {
+        (self.on_color + self.off_color) * 0.5
+    }
+}
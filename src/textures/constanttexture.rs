@@ -0,0 +1,23 @@
+use crate::common::*;
+use crate::textures::Texture;
+
+#[derive(Debug, Clone)]
+pub struct ConstantTexture {
+    value: Spectrum,
+}
+
+impl ConstantTexture {
+    pub fn new(value: Spectrum) -> ConstantTexture {
+        ConstantTexture { value }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn evaluate(&self, _uv: Point2) -> Spectrum {
+        self.value
+    }
+
+    fn average(&self) -> Spectrum {
+        self.value
+    }
+}
@@ -0,0 +1,15 @@
+use crate::common::*;
+use std::fmt::Debug;
+
+pub mod bitmaptexture;
+pub mod bumpmap;
+pub mod checkertexture;
+pub mod constanttexture;
+
+//Spatially varying quantity looked up with the surface texture coordinates
+pub trait Texture: Debug + Send + Sync {
+    fn evaluate(&self, uv: Point2) -> Spectrum;
+
+    //Mean value over the unit uv square
+    fn average(&self) -> Spectrum;
+}
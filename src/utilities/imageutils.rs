@@ -1,17 +1,32 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::common::*;
-use byteorder::{ByteOrder, LittleEndian};
+use crate::film::tonemapping::srgb_eotf;
+use crate::film::FilmPixel;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, Blocks, Compression, Encoding, FlatSamples, Image, Layer,
+    LayerAttributes, LineOrder, Vec2, WritableImage,
+};
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 
+//Floating point RGB image read from disk, stored top row first like the film
 #[derive(Debug, Default)]
-#[allow(dead_code)]
-struct PfmInfo {
-    image: Vec<Spectrum>,
-    width: i32,
-    height: i32,
+pub struct HdrImage {
+    pub pixels: Vec<Spectrum>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl HdrImage {
+    //Pixel at column x and row y counted from the top, clamped to the image
+    pub fn get(&self, x: i32, y: i32) -> Spectrum {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        self.pixels[(y * self.width + x) as usize]
+    }
 }
 
 #[derive(Debug, Default)]
@@ -67,3 +82,884 @@ pub fn write_pfm(
 
     Ok(())
 }
+
+//Writes 8 bit sRGB pixels, expects display values in [0, 1] such as the output of a tonemapper
+pub fn write_png(
+    file_path: PathBuf,
+    pixels: Vec<Spectrum>,
+    width: i32,
+    height: i32,
+) -> Result<(), Box<dyn Error>> {
+    assert_eq!(pixels.len(), (width * height) as usize);
+
+    std::fs::create_dir_all(PathBuf::from(file_path.parent().unwrap()))?;
+    let file = File::create(&file_path)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+
+    let to_byte = |value: fp| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut data: Vec<u8> = Vec::with_capacity((width * height * 3) as usize);
+    //The image buffer is already stored top row first like PNG, unlike PFM
+    for pixelvalue in &pixels {
+        data.extend_from_slice(&[
+            to_byte(pixelvalue.x),
+            to_byte(pixelvalue.y),
+            to_byte(pixelvalue.z),
+        ]);
+    }
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
+
+//How EXR files are stored
+#[derive(Debug, Clone, Copy)]
+pub struct ExrSettings {
+    pub compression: Compression,
+    //16 bit half floats instead of 32 bit floats
+    pub half_float: bool,
+}
+
+impl Default for ExrSettings {
+    fn default() -> Self {
+        ExrSettings {
+            compression: Compression::ZIP16,
+            half_float: false,
+        }
+    }
+}
+
+//Compression names as used in scene files
+pub fn exr_compression_from_name(name: &str) -> Option<Compression> {
+    match name.to_ascii_lowercase().as_str() {
+        "none" => Some(Compression::Uncompressed),
+        "rle" => Some(Compression::RLE),
+        "zips" => Some(Compression::ZIP1),
+        "zip" => Some(Compression::ZIP16),
+        "piz" => Some(Compression::PIZ),
+        _ => None,
+    }
+}
+
+//Writes a scanline EXR with the beauty pass as R, G, B and the AOVs as albedo.R, albedo.G,
+//albedo.B, normal.X, normal.Y, normal.Z, depth.Z and variance.R, variance.G, variance.B
+pub fn write_exr(
+    file_path: PathBuf,
+    pixels: &[FilmPixel],
+    width: i32,
+    height: i32,
+    settings: ExrSettings,
+) -> Result<(), Box<dyn Error>> {
+    assert_eq!(pixels.len(), (width * height) as usize);
+
+    std::fs::create_dir_all(PathBuf::from(file_path.parent().unwrap()))?;
+
+    //The image buffer is already stored top row first like EXR
+    let channel = |name: &str, component: &dyn Fn(&FilmPixel) -> fp| -> AnyChannel<FlatSamples> {
+        let samples = if settings.half_float {
+            FlatSamples::F16(
+                pixels
+                    .iter()
+                    .map(|pixel| f16::from_f64(component(pixel)))
+                    .collect(),
+            )
+        } else {
+            FlatSamples::F32(pixels.iter().map(|pixel| component(pixel) as f32).collect())
+        };
+        AnyChannel::new(name, samples)
+    };
+    let channels: Vec<AnyChannel<FlatSamples>> = vec![
+        channel("R", &|pixel| pixel.radiance.x),
+        channel("G", &|pixel| pixel.radiance.y),
+        channel("B", &|pixel| pixel.radiance.z),
+        channel("albedo.R", &|pixel| pixel.albedo.x),
+        channel("albedo.G", &|pixel| pixel.albedo.y),
+        channel("albedo.B", &|pixel| pixel.albedo.z),
+        channel("normal.X", &|pixel| pixel.normal.x),
+        channel("normal.Y", &|pixel| pixel.normal.y),
+        channel("normal.Z", &|pixel| pixel.normal.z),
+        channel("depth.Z", &|pixel| pixel.depth),
+        channel("variance.R", &|pixel| pixel.variance.x),
+        channel("variance.G", &|pixel| pixel.variance.y),
+        channel("variance.B", &|pixel| pixel.variance.z),
+    ];
+
+    let layer = Layer::new(
+        Vec2(width as usize, height as usize),
+        LayerAttributes::default(),
+        //exr defaults to 64x64 tiles
+        Encoding {
+            compression: settings.compression,
+            blocks: Blocks::ScanLines,
+            line_order: LineOrder::Increasing,
+        },
+        AnyChannels::sort(channels.into()),
+    );
+    Image::from_layer(layer).write().to_file(&file_path)?;
+
+    Ok(())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//Reads colour (PF) and greyscale (Pf) PFM files. The sign of the scale gives the byte order,
+//negative for little endian, and scanlines are stored bottom to top.
+pub fn read_pfm(file_path: &Path) -> io::Result<HdrImage> {
+    let data: Vec<u8> = std::fs::read(file_path)?;
+
+    //The header is four whitespace separated tokens, type, width, height and scale, followed by
+    //a single whitespace character
+    let mut tokens: Vec<String> = vec![];
+    let mut position: usize = 0;
+    while tokens.len() < 4 {
+        while position < data.len() && data[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        let start = position;
+        while position < data.len() && !data[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        if start == position {
+            return Err(invalid_data("truncated PFM header".to_string()));
+        }
+        tokens.push(String::from_utf8_lossy(&data[start..position]).into_owned());
+    }
+    position += 1;
+
+    let channels: usize = match tokens[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        other => return Err(invalid_data(format!("unknown PFM type {}", other))),
+    };
+    let width: i32 = tokens[1]
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PFM width {}", tokens[1])))?;
+    let height: i32 = tokens[2]
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PFM height {}", tokens[2])))?;
+    if width < 1 || height < 1 {
+        return Err(invalid_data(format!(
+            "invalid PFM resolution {}x{}",
+            width, height
+        )));
+    }
+    let scale: f32 = match tokens[3].parse::<f32>() {
+        Ok(scale) if scale != 0.0 && scale.is_finite() => scale,
+        _ => return Err(invalid_data(format!("invalid PFM scale {}", tokens[3]))),
+    };
+
+    let values_count: usize = width as usize * height as usize * channels;
+    let raster: &[u8] = data
+        .get(position..position + values_count * 4)
+        .ok_or_else(|| invalid_data("truncated PFM raster".to_string()))?;
+    let read_value = |index: usize| -> fp {
+        let bytes = &raster[index * 4..index * 4 + 4];
+        let value = if scale < 0.0 {
+            LittleEndian::read_f32(bytes)
+        } else {
+            BigEndian::read_f32(bytes)
+        };
+        fp::from(value)
+    };
+
+    let mut pixels: Vec<Spectrum> = Vec::with_capacity((width * height) as usize);
+    for i in 0..(height) {
+        //The first scanline in the file is the bottom row of the image
+        let row: usize = (height - i - 1) as usize;
+        for j in 0..(width as usize) {
+            let first = (row * width as usize + j) * channels;
+            pixels.push(if channels == 3 {
+                Spectrum::new(
+                    read_value(first),
+                    read_value(first + 1),
+                    read_value(first + 2),
+                )
+            } else {
+                Spectrum::from(read_value(first))
+            });
+        }
+    }
+
+    Ok(HdrImage {
+        pixels,
+        width,
+        height,
+    })
+}
+
+//Reads Radiance RGBE (.hdr) files with flat or run length encoded scanlines. Rows may be stored
+//top to bottom (-Y, the usual case) or bottom to top (+Y), columns left to right or right to left.
+pub fn read_rgbe(file_path: &Path) -> io::Result<HdrImage> {
+    let data: Vec<u8> = std::fs::read(file_path)?;
+    let mut position: usize = 0;
+    let next_line = |position: &mut usize| -> io::Result<String> {
+        let start = *position;
+        let end = data[start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map(|offset| start + offset)
+            .ok_or_else(|| invalid_data("truncated RGBE header".to_string()))?;
+        *position = end + 1;
+        Ok(String::from_utf8_lossy(&data[start..end])
+            .trim()
+            .to_string())
+    };
+
+    //Header lines up to an empty line, then the resolution line
+    let magic = next_line(&mut position)?;
+    if !magic.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file".to_string()));
+    }
+    loop {
+        let line = next_line(&mut position)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported RGBE format {}", format)));
+            }
+        }
+    }
+    let resolution_line = next_line(&mut position)?;
+    let resolution: Vec<&str> = resolution_line.split_whitespace().collect();
+    let (rows_top_first, height, columns_left_first, width) = match resolution[..] {
+        [y_axis @ ("-Y" | "+Y"), height, x_axis @ ("+X" | "-X"), width] => {
+            (y_axis == "-Y", height, x_axis == "+X", width)
+        }
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported RGBE resolution {}",
+                resolution_line
+            )))
+        }
+    };
+    let parse_size = |size: &str| -> io::Result<i32> {
+        match size.parse::<i32>() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(invalid_data(format!(
+                "invalid RGBE resolution {}",
+                resolution_line
+            ))),
+        }
+    };
+    let width: i32 = parse_size(width)?;
+    let height: i32 = parse_size(height)?;
+
+    let mut pixels: Vec<Spectrum> = vec![Spectrum::default(); (width * height) as usize];
+    let mut scanline: Vec<[u8; 4]> = vec![[0; 4]; width as usize];
+    for i in 0..(height) {
+        read_rgbe_scanline(&data, &mut position, &mut scanline)?;
+        let row: i32 = if rows_top_first { i } else { height - i - 1 };
+        for (j, rgbe) in scanline.iter().enumerate() {
+            let column: i32 = if columns_left_first {
+                j as i32
+            } else {
+                width - j as i32 - 1
+            };
+            pixels[(row * width + column) as usize] = rgbe_to_spectrum(*rgbe);
+        }
+    }
+
+    Ok(HdrImage {
+        pixels,
+        width,
+        height,
+    })
+}
+
+fn read_rgbe_scanline(
+    data: &[u8],
+    position: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> io::Result<()> {
+    let truncated = || invalid_data("truncated RGBE scanline".to_string());
+    let next_byte = |position: &mut usize| -> io::Result<u8> {
+        let byte = *data.get(*position).ok_or_else(truncated)?;
+        *position += 1;
+        Ok(byte)
+    };
+    let width: usize = scanline.len();
+
+    //Run length encoded scanlines start with 2, 2 and the scanline width, then store the four
+    //components one after another
+    let header: &[u8] = data.get(*position..*position + 4).ok_or_else(truncated)?;
+    if (8..0x8000).contains(&width)
+        && header[0] == 2
+        && header[1] == 2
+        && header[2] & 0x80 == 0
+        && ((header[2] as usize) << 8 | header[3] as usize) == width
+    {
+        *position += 4;
+        for component in 0..4 {
+            let mut x: usize = 0;
+            while x < width {
+                let count = next_byte(position)?;
+                if count > 128 {
+                    //A run of one repeated value
+                    let run: usize = (count - 128) as usize;
+                    let value = next_byte(position)?;
+                    if x + run > width {
+                        return Err(invalid_data(
+                            "RGBE run past the end of a scanline".to_string(),
+                        ));
+                    }
+                    for pixel in &mut scanline[x..x + run] {
+                        pixel[component] = value;
+                    }
+                    x += run;
+                } else {
+                    //A run of literal values
+                    let run: usize = count as usize;
+                    if run == 0 || x + run > width {
+                        return Err(invalid_data("invalid RGBE literal run".to_string()));
+                    }
+                    for pixel in &mut scanline[x..x + run] {
+                        pixel[component] = next_byte(position)?;
+                    }
+                    x += run;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    //Flat scanlines, possibly with the old style 1, 1, 1, count markers repeating the previous pixel
+    let mut x: usize = 0;
+    let mut shift: u32 = 0;
+    while x < width {
+        let rgbe: [u8; 4] = [
+            next_byte(position)?,
+            next_byte(position)?,
+            next_byte(position)?,
+            next_byte(position)?,
+        ];
+        if rgbe[0] == 1 && rgbe[1] == 1 && rgbe[2] == 1 && x > 0 {
+            let run: usize = (rgbe[3] as usize) << shift;
+            if x + run > width {
+                return Err(invalid_data(
+                    "RGBE run past the end of a scanline".to_string(),
+                ));
+            }
+            let previous = scanline[x - 1];
+            for pixel in &mut scanline[x..x + run] {
+                *pixel = previous;
+            }
+            x += run;
+            shift += 8;
+        } else {
+            scanline[x] = rgbe;
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(())
+}
+
+//Shared exponent to floating point, with Greg Ward's half step rounding of the mantissas
+fn rgbe_to_spectrum(rgbe: [u8; 4]) -> Spectrum {
+    if rgbe[3] == 0 {
+        return Spectrum::default();
+    }
+    let f: fp = fp::powi(2.0, rgbe[3] as i32 - (128 + 8));
+    Spectrum::new(
+        (fp::from(rgbe[0]) + 0.5) * f,
+        (fp::from(rgbe[1]) + 0.5) * f,
+        (fp::from(rgbe[2]) + 0.5) * f,
+    )
+}
+
+//Reads 8 and 16 bit PNGs of any colour type, dropping the alpha channel. Colours are decoded from
+//sRGB unless the image holds data such as heights, which is kept as stored, scaled to [0, 1].
+pub fn read_png(file_path: &Path, srgb: bool) -> io::Result<HdrImage> {
+    let mut decoder = png::Decoder::new(File::open(file_path)?);
+    //Palettes, low bit depths and transparency chunks become plain 8 bit samples
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|error| invalid_data(format!("invalid PNG: {}", error)))?;
+    let mut data: Vec<u8> = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut data)
+        .map_err(|error| invalid_data(format!("invalid PNG: {}", error)))?;
+    let channels: usize = frame.color_type.samples();
+    let sixteen_bit: bool = frame.bit_depth == png::BitDepth::Sixteen;
+    let decode = |index: usize| -> fp {
+        let value: fp = if sixteen_bit {
+            fp::from(BigEndian::read_u16(&data[index * 2..index * 2 + 2])) / 65535.0
+        } else {
+            fp::from(data[index]) / 255.0
+        };
+        if srgb {
+            srgb_eotf(value)
+        } else {
+            value
+        }
+    };
+
+    let width: i32 = frame.width as i32;
+    let height: i32 = frame.height as i32;
+    let row_samples: usize = frame.line_size / if sixteen_bit { 2 } else { 1 };
+    let mut pixels: Vec<Spectrum> = Vec::with_capacity((width * height) as usize);
+    for row in 0..(height as usize) {
+        for column in 0..(width as usize) {
+            let first = row * row_samples + column * channels;
+            //Greyscale images, with or without alpha, have a single colour sample
+            pixels.push(if channels >= 3 {
+                Spectrum::new(decode(first), decode(first + 1), decode(first + 2))
+            } else {
+                Spectrum::from(decode(first))
+            });
+        }
+    }
+
+    Ok(HdrImage {
+        pixels,
+        width,
+        height,
+    })
+}
+
+//Reads 8 bit greyscale and RGB JPEGs, and the rare 16 bit greyscale ones, decoding colours from
+//sRGB like read_png. CMYK JPEGs are not supported.
+pub fn read_jpeg(file_path: &Path, srgb: bool) -> io::Result<HdrImage> {
+    let mut decoder = jpeg_decoder::Decoder::new(io::BufReader::new(File::open(file_path)?));
+    let data: Vec<u8> = decoder
+        .decode()
+        .map_err(|error| invalid_data(format!("invalid JPEG: {}", error)))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| invalid_data("invalid JPEG: missing frame header".to_string()))?;
+    let decode = |value: fp| if srgb { srgb_eotf(value) } else { value };
+    let pixels: Vec<Spectrum> = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => data
+            .iter()
+            .map(|value| Spectrum::from(decode(fp::from(*value) / 255.0)))
+            .collect(),
+        jpeg_decoder::PixelFormat::L16 => data
+            .chunks_exact(2)
+            .map(|bytes| {
+                let value = u16::from_ne_bytes([bytes[0], bytes[1]]);
+                Spectrum::from(decode(fp::from(value) / 65535.0))
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => data
+            .chunks_exact(3)
+            .map(|rgb| {
+                Spectrum::new(
+                    decode(fp::from(rgb[0]) / 255.0),
+                    decode(fp::from(rgb[1]) / 255.0),
+                    decode(fp::from(rgb[2]) / 255.0),
+                )
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "CMYK JPEGs are not supported",
+            ))
+        }
+    };
+    let width: i32 = i32::from(info.width);
+    let height: i32 = i32::from(info.height);
+    if pixels.len() != (width * height) as usize {
+        return Err(invalid_data("truncated JPEG".to_string()));
+    }
+
+    Ok(HdrImage {
+        pixels,
+        width,
+        height,
+    })
+}
+
+//Reads any of the supported image formats, picked by file extension. PNGs and JPEGs hold sRGB
+//encoded colours unless srgb is false, for data such as heights.
+pub fn read_image(file_path: &Path, srgb: bool) -> io::Result<HdrImage> {
+    match file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("pfm") => read_pfm(file_path),
+        Some("hdr") | Some("rgbe") => read_rgbe(file_path),
+        Some("png") => read_png(file_path, srgb),
+        Some("jpg") | Some("jpeg") => read_jpeg(file_path, srgb),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unsupported image format",
+        )),
+    }
+}
+
+//Reads an image holding colours
+pub fn read_hdr_image(file_path: &Path) -> io::Result<HdrImage> {
+    read_image(file_path, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Unique path in the system temporary directory, removed again by the test
+    fn temporary_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sayo_pbr_rs_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn write_exr_is_scanline_and_round_trips_channels() {
+        use exr::prelude::{read, ReadChannels, ReadLayers};
+
+        let (width, height) = (3, 2);
+        let pixels: Vec<FilmPixel> = (0..width * height)
+            .map(|i| {
+                let i = fp::from(i);
+                FilmPixel {
+                    radiance: Spectrum::new(i, i + 0.25, i + 0.5),
+                    albedo: Spectrum::new(0.1 * i, 0.2, 0.3),
+                    normal: Vec3::new(0.0, 1.0, -i),
+                    depth: 2.0 * i,
+                    variance: Spectrum::from(0.5 * i),
+                }
+            })
+            .collect();
+        let file = temporary_file("roundtrip.exr");
+        write_exr(file.clone(), &pixels, width, height, ExrSettings::default()).unwrap();
+
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_file(&file)
+            .unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        let layer = &image.layer_data;
+        assert_eq!(layer.encoding.blocks, Blocks::ScanLines);
+        assert_eq!(layer.encoding.line_order, LineOrder::Increasing);
+        assert_eq!(layer.size, Vec2(width as usize, height as usize));
+        let channel = |name: &str| -> Vec<fp> {
+            let channel = layer
+                .channel_data
+                .list
+                .iter()
+                .find(|channel| channel.name.eq(name))
+                .unwrap_or_else(|| panic!("missing channel {}", name));
+            channel.sample_data.values_as_f32().map(fp::from).collect()
+        };
+        let component = |name: &str, pixel: &FilmPixel| -> fp {
+            match name {
+                "R" => pixel.radiance.x,
+                "G" => pixel.radiance.y,
+                "B" => pixel.radiance.z,
+                "albedo.R" => pixel.albedo.x,
+                "albedo.G" => pixel.albedo.y,
+                "albedo.B" => pixel.albedo.z,
+                "normal.X" => pixel.normal.x,
+                "normal.Y" => pixel.normal.y,
+                _ => pixel.normal.z,
+            }
+        };
+        for name in [
+            "R", "G", "B", "albedo.R", "albedo.G", "albedo.B", "normal.X", "normal.Y", "normal.Z",
+        ] {
+            let values = channel(name);
+            for (value, pixel) in values.iter().zip(&pixels) {
+                assert!(
+                    (value - component(name, pixel)).abs() < 1e-6,
+                    "{}: {} != {}",
+                    name,
+                    value,
+                    component(name, pixel)
+                );
+            }
+            assert_eq!(values.len(), pixels.len());
+        }
+    }
+
+    fn rgb(spectrum: Spectrum) -> [fp; 3] {
+        [spectrum.x, spectrum.y, spectrum.z]
+    }
+
+    //Reads an image from a temporary file holding the given bytes
+    fn read_bytes(
+        name: &str,
+        bytes: &[u8],
+        read: fn(&Path) -> io::Result<HdrImage>,
+    ) -> io::Result<HdrImage> {
+        let file = temporary_file(name);
+        std::fs::write(&file, bytes).unwrap();
+        let image = read(&file);
+        std::fs::remove_file(&file).unwrap();
+        image
+    }
+
+    fn pfm_bytes(header: &str, values: &[f32], little_endian: bool) -> Vec<u8> {
+        let mut bytes: Vec<u8> = header.as_bytes().to_vec();
+        for value in values {
+            let mut buffer = [0; 4];
+            if little_endian {
+                LittleEndian::write_f32(&mut buffer, *value);
+            } else {
+                BigEndian::write_f32(&mut buffer, *value);
+            }
+            bytes.extend_from_slice(&buffer);
+        }
+        bytes
+    }
+
+    #[test]
+    fn read_pfm_colour_big_endian_flips_rows() {
+        //Bottom row first in the file
+        let values = [
+            1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0,
+        ];
+        let bytes = pfm_bytes("PF\n2 2\n1.0\n", &values, false);
+        let image = read_bytes("colour.pfm", &bytes, read_pfm).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(rgb(image.get(0, 0)), rgb(Spectrum::new(7.0, 8.0, 9.0)));
+        assert_eq!(rgb(image.get(1, 0)), rgb(Spectrum::new(10.0, 11.0, 12.0)));
+        assert_eq!(rgb(image.get(0, 1)), rgb(Spectrum::new(1.0, 2.0, 3.0)));
+        assert_eq!(rgb(image.get(1, 1)), rgb(Spectrum::new(4.0, 5.0, 6.0)));
+    }
+
+    #[test]
+    fn read_pfm_greyscale_little_endian() {
+        let values = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0];
+        let bytes = pfm_bytes("Pf 3 2 -1.0\n", &values, true);
+        let image = read_bytes("grey.pfm", &bytes, read_pfm).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(rgb(image.get(0, 0)), rgb(Spectrum::from(1.0)));
+        assert_eq!(rgb(image.get(2, 0)), rgb(Spectrum::from(2.0)));
+        assert_eq!(rgb(image.get(0, 1)), rgb(Spectrum::from(0.25)));
+        assert_eq!(rgb(image.get(2, 1)), rgb(Spectrum::from(0.75)));
+    }
+
+    #[test]
+    fn read_pfm_little_and_big_endian_agree() {
+        let values = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let little = read_bytes(
+            "little.pfm",
+            &pfm_bytes("PF\n2 1\n-1\n", &values, true),
+            read_pfm,
+        )
+        .unwrap();
+        let big = read_bytes(
+            "big.pfm",
+            &pfm_bytes("PF\n2 1\n1\n", &values, false),
+            read_pfm,
+        )
+        .unwrap();
+        assert_eq!(
+            little
+                .pixels
+                .iter()
+                .map(|pixel| rgb(*pixel))
+                .collect::<Vec<_>>(),
+            big.pixels
+                .iter()
+                .map(|pixel| rgb(*pixel))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(little.get(1, 0).z, fp::from(0.6f32));
+    }
+
+    #[test]
+    fn read_pfm_rejects_truncated_raster() {
+        let bytes = pfm_bytes("PF\n2 2\n-1\n", &[1.0; 11], true);
+        let error = read_bytes("truncated.pfm", &bytes, read_pfm).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("truncated PFM raster"));
+    }
+
+    #[test]
+    fn read_pfm_rejects_bad_headers() {
+        for (header, message) in [
+            ("P6\n1 1\n-1\n", "unknown PFM type"),
+            ("PF\nx 1\n-1\n", "invalid PFM width"),
+            ("PF\n1 y\n-1\n", "invalid PFM height"),
+            ("PF\n0 1\n-1\n", "invalid PFM resolution"),
+            ("PF\n1 1\n0\n", "invalid PFM scale"),
+            ("PF\n1 1", "truncated PFM header"),
+        ] {
+            let bytes = pfm_bytes(header, &[], true);
+            let error = read_bytes("header.pfm", &bytes, read_pfm).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(
+                error.to_string().contains(message),
+                "{:?}: {}",
+                header,
+                error
+            );
+        }
+    }
+
+    fn rgbe_bytes(resolution: &str, raster: &[u8]) -> Vec<u8> {
+        let mut bytes: Vec<u8> =
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes();
+        bytes.extend_from_slice(raster);
+        bytes
+    }
+
+    const RED: [u8; 4] = [128, 0, 0, 129];
+    const GREEN: [u8; 4] = [0, 128, 0, 130];
+    const BLUE: [u8; 4] = [0, 0, 128, 131];
+    const WHITE: [u8; 4] = [128, 128, 128, 128];
+
+    fn assert_pixels(image: &HdrImage, expected: &[[u8; 4]]) {
+        assert_eq!(image.pixels.len(), expected.len());
+        for (i, (pixel, rgbe)) in image.pixels.iter().zip(expected).enumerate() {
+            assert_eq!(rgb(*pixel), rgb(rgbe_to_spectrum(*rgbe)), "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn read_rgbe_flat_scanlines() {
+        let raster = [RED, GREEN, BLUE, WHITE].concat();
+        let image = read_bytes("flat.hdr", &rgbe_bytes("-Y 2 +X 2", &raster), read_rgbe).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_pixels(&image, &[RED, GREEN, BLUE, WHITE]);
+        //Mantissas are rounded to the middle of their step, even zero ones
+        assert_eq!(rgb(image.get(0, 0)), [1.00390625, 0.00390625, 0.00390625]);
+    }
+
+    #[test]
+    fn read_rgbe_orientations() {
+        let raster = [RED, GREEN, BLUE, WHITE].concat();
+        //Rows stored bottom to top
+        let image = read_bytes("plus_y.hdr", &rgbe_bytes("+Y 2 +X 2", &raster), read_rgbe).unwrap();
+        assert_pixels(&image, &[BLUE, WHITE, RED, GREEN]);
+        //Columns stored right to left
+        let image =
+            read_bytes("minus_x.hdr", &rgbe_bytes("-Y 2 -X 2", &raster), read_rgbe).unwrap();
+        assert_pixels(&image, &[GREEN, RED, WHITE, BLUE]);
+        let image = read_bytes("both.hdr", &rgbe_bytes("+Y 2 -X 2", &raster), read_rgbe).unwrap();
+        assert_pixels(&image, &[WHITE, BLUE, GREEN, RED]);
+        let error =
+            read_bytes("axes.hdr", &rgbe_bytes("+X 2 -Y 2", &raster), read_rgbe).unwrap_err();
+        assert!(error.to_string().contains("unsupported RGBE resolution"));
+    }
+
+    #[test]
+    fn read_rgbe_new_style_run_length_encoding() {
+        //An 8 pixel scanline, the shortest one that may be run length encoded. Each component mixes
+        //runs of a repeated value with literal runs.
+        let mut raster: Vec<u8> = vec![2, 2, 0, 8];
+        for component in 0..4 {
+            let repeated: u8 = 10 * component as u8 + 1;
+            raster.extend_from_slice(&[128 + 5, repeated, 3]);
+            raster.extend((0..3).map(|i| 100 + 10 * component as u8 + i));
+        }
+        let image = read_bytes("rle.hdr", &rgbe_bytes("-Y 1 +X 8", &raster), read_rgbe).unwrap();
+        let expected: Vec<[u8; 4]> = (0..8)
+            .map(|x| {
+                let mut rgbe = [0; 4];
+                for (component, value) in rgbe.iter_mut().enumerate() {
+                    *value = if x < 5 {
+                        10 * component as u8 + 1
+                    } else {
+                        100 + 10 * component as u8 + (x - 5)
+                    };
+                }
+                rgbe
+            })
+            .collect();
+        assert_pixels(&image, &expected);
+    }
+
+    #[test]
+    fn read_rgbe_old_style_repeat_markers() {
+        //RED followed by a marker repeating it 3 times
+        let raster = [RED, [1, 1, 1, 3], GREEN, GREEN].concat();
+        let image = read_bytes("repeat.hdr", &rgbe_bytes("-Y 1 +X 6", &raster), read_rgbe).unwrap();
+        assert_pixels(&image, &[RED, RED, RED, RED, GREEN, GREEN]);
+
+        //Consecutive markers make up one longer run, each shifted 8 bits further: 4 + (1 << 8)
+        let raster = [BLUE, [1, 1, 1, 4], [1, 1, 1, 1], WHITE].concat();
+        let image = read_bytes(
+            "shifted.hdr",
+            &rgbe_bytes("-Y 1 +X 262", &raster),
+            read_rgbe,
+        )
+        .unwrap();
+        let mut expected = vec![BLUE; 261];
+        expected.push(WHITE);
+        assert_pixels(&image, &expected);
+
+        //A literal pixel in between resets the shift
+        let raster = [RED, [1, 1, 1, 1], GREEN, [1, 1, 1, 1]].concat();
+        let image = read_bytes("reset.hdr", &rgbe_bytes("-Y 1 +X 4", &raster), read_rgbe).unwrap();
+        assert_pixels(&image, &[RED, RED, GREEN, GREEN]);
+    }
+
+    #[test]
+    fn read_rgbe_rejects_runs_past_the_scanline() {
+        let raster = [RED, [1, 1, 1, 5]].concat();
+        let error =
+            read_bytes("old_run.hdr", &rgbe_bytes("-Y 1 +X 4", &raster), read_rgbe).unwrap_err();
+        assert!(error.to_string().contains("run past the end"), "{}", error);
+
+        let raster = [2, 2, 0, 8, 128 + 9, 0];
+        let error =
+            read_bytes("new_run.hdr", &rgbe_bytes("-Y 1 +X 8", &raster), read_rgbe).unwrap_err();
+        assert!(error.to_string().contains("run past the end"), "{}", error);
+
+        let raster = [2, 2, 0, 8, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let error =
+            read_bytes("literal.hdr", &rgbe_bytes("-Y 1 +X 8", &raster), read_rgbe).unwrap_err();
+        assert!(
+            error.to_string().contains("invalid RGBE literal run"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn read_rgbe_rejects_truncated_files() {
+        //Second scanline missing a pixel
+        let raster = [RED, GREEN, BLUE].concat();
+        let error =
+            read_bytes("short.hdr", &rgbe_bytes("-Y 2 +X 2", &raster), read_rgbe).unwrap_err();
+        assert!(
+            error.to_string().contains("truncated RGBE scanline"),
+            "{}",
+            error
+        );
+
+        //Run length encoded scanline ending inside a component
+        let raster = [2, 2, 0, 8, 128 + 8, 0, 128 + 8];
+        let error = read_bytes(
+            "short_rle.hdr",
+            &rgbe_bytes("-Y 1 +X 8", &raster),
+            read_rgbe,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("truncated RGBE scanline"),
+            "{}",
+            error
+        );
+
+        let error = read_bytes("header.hdr", b"#?RADIANCE\n\n-Y 1", read_rgbe).unwrap_err();
+        assert!(
+            error.to_string().contains("truncated RGBE header"),
+            "{}",
+            error
+        );
+        let error = read_bytes("magic.hdr", b"P6\n", read_rgbe).unwrap_err();
+        assert!(
+            error.to_string().contains("not a Radiance HDR file"),
+            "{}",
+            error
+        );
+    }
+}
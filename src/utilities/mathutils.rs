@@ -119,6 +119,18 @@ impl ops::Div<fp> for Vector3 {
     }
 }
 
+impl ops::Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Vector3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
 impl ops::AddAssign for Vector3 {
     fn add_assign(&mut self, rhs: Self) {
         *self = Self {
@@ -139,6 +151,14 @@ impl ops::SubAssign for Vector3 {
     }
 }
 
+impl ops::MulAssign for Vector3 {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.x *= rhs.x;
+        self.y *= rhs.y;
+        self.z *= rhs.z;
+    }
+}
+
 impl ops::MulAssign<fp> for Vector3 {
     fn mul_assign(&mut self, rhs: fp) {
         self.x *= rhs;
@@ -245,6 +265,74 @@ impl Vector3 {
             z: fp::abs(self.z),
         }
     }
+
+    pub fn length_squared(&self) -> fp {
+        self.dot(*self)
+    }
+
+    pub fn length(&self) -> fp {
+        self.length_squared().sqrt()
+    }
+
+    pub fn max_component(&self) -> fp {
+        fp::max(fp::max(self.x, self.y), self.z)
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.x == 0.0 && self.y == 0.0 && self.z == 0.0
+    }
+
+    //Rec. 709 luminance of a linear RGB spectrum
+    pub fn luminance(&self) -> fp {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+}
+
+//Orthonormal basis around a normal, used to move directions in and out of the local shading space
+//where the normal is the +z axis
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Frame {
+    pub s: Vector3,
+    pub t: Vector3,
+    pub n: Vector3,
+}
+
+impl Frame {
+    pub fn from_normal(n: Vector3) -> Frame {
+        let mut s: Vector3 = Default::default();
+        let mut t: Vector3 = Default::default();
+        coordinate_system(n, &mut s, &mut t);
+        Frame {
+            s,
+            t,
+            n: n.normalize(),
+        }
+    }
+
+    //Frame with s along the part of the tangent perpendicular to n, for BSDFs that are not
+    //rotationally symmetric. Falls back to an arbitrary s when the tangent has no such part.
+    pub fn from_normal_and_tangent(n: Vector3, tangent: Vector3) -> Frame {
+        let n: Vector3 = n.normalize();
+        let s: Vector3 = tangent - n * n.dot(tangent);
+        let length: fp = s.length();
+        if !length.is_finite() || length <= 1e-9 * tangent.length() {
+            return Frame::from_normal(n);
+        }
+        let s: Vector3 = s / length;
+        Frame {
+            s,
+            t: n.cross(s),
+            n,
+        }
+    }
+
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(v.dot(self.s), v.dot(self.t), v.dot(self.n))
+    }
+
+    pub fn to_world(&self, v: Vector3) -> Vector3 {
+        self.s * v.x + self.t * v.y + self.n * v.z
+    }
 }
 
 //Utility function to construct a coordinate system by making a 2nd vector
@@ -378,3 +466,161 @@ impl Vector2 {
         }
     }
 }
+
+//Affine transform stored as a row-major 4x4 matrix together with its inverse, so that normals and
+//rays going from world to object space never need a matrix inversion
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    m: [[fp; 4]; 4],
+    m_inv: [[fp; 4]; 4],
+}
+
+const IDENTITY_MATRIX: [[fp; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn multiply_matrices(a: &[[fp; 4]; 4], b: &[[fp; 4]; 4]) -> [[fp; 4]; 4] {
+    let mut result: [[fp; 4]; 4] = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            *entry = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+fn transpose_matrix(a: &[[fp; 4]; 4]) -> [[fp; 4]; 4] {
+    let mut result: [[fp; 4]; 4] = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            *entry = a[j][i];
+        }
+    }
+    result
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl ops::Mul for Transform {
+    type Output = Transform;
+
+    //Applies rhs first, then self
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            m: multiply_matrices(&self.m, &rhs.m),
+            m_inv: multiply_matrices(&rhs.m_inv, &self.m_inv),
+        }
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            m: IDENTITY_MATRIX,
+            m_inv: IDENTITY_MATRIX,
+        }
+    }
+
+    pub fn translate(delta: Vector3) -> Transform {
+        let mut m: [[fp; 4]; 4] = IDENTITY_MATRIX;
+        let mut m_inv: [[fp; 4]; 4] = IDENTITY_MATRIX;
+        for i in 0..3 {
+            m[i][3] = delta[i as i32];
+            m_inv[i][3] = -delta[i as i32];
+        }
+        Transform { m, m_inv }
+    }
+
+    pub fn scale(factors: Vector3) -> Transform {
+        let mut m: [[fp; 4]; 4] = IDENTITY_MATRIX;
+        let mut m_inv: [[fp; 4]; 4] = IDENTITY_MATRIX;
+        for i in 0..3 {
+            m[i][i] = factors[i as i32];
+            m_inv[i][i] = 1.0 / factors[i as i32];
+        }
+        Transform { m, m_inv }
+    }
+
+    //Rotation by the given angle in degrees around one of the coordinate axes (0 = x, 1 = y, 2 = z),
+    //counterclockwise when looking down the axis towards the origin
+    pub fn rotate(axis: usize, degrees: fp) -> Transform {
+        let (sin_theta, cos_theta) = degrees.to_radians().sin_cos();
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut m: [[fp; 4]; 4] = IDENTITY_MATRIX;
+        m[a][a] = cos_theta;
+        m[a][b] = -sin_theta;
+        m[b][a] = sin_theta;
+        m[b][b] = cos_theta;
+        //Rotations are orthogonal
+        Transform {
+            m,
+            m_inv: transpose_matrix(&m),
+        }
+    }
+
+    //The transform of a Tungsten scene entry: scale, then rotate, then translate. Rotation angles
+    //are Euler angles in degrees applied in Tungsten's rotYXZ order, which turns the y axis the
+    //opposite way to the other two.
+    pub fn from_position_rotation_scale(
+        position: Vector3,
+        rotation: Vector3,
+        scale: Vector3,
+    ) -> Transform {
+        Transform::translate(position)
+            * Transform::rotate(1, -rotation.y)
+            * Transform::rotate(0, rotation.x)
+            * Transform::rotate(2, rotation.z)
+            * Transform::scale(scale)
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn transform_point(&self, p: Vector3) -> Vector3 {
+        let m = &self.m;
+        Vector3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: Vector3) -> Vector3 {
+        let m = &self.m;
+        Vector3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    //Normals go through the inverse transpose to stay perpendicular to the surface. The result is
+    //not normalized.
+    pub fn transform_normal(&self, n: Vector3) -> Vector3 {
+        let m_inv = &self.m_inv;
+        Vector3::new(
+            m_inv[0][0] * n.x + m_inv[1][0] * n.y + m_inv[2][0] * n.z,
+            m_inv[0][1] * n.x + m_inv[1][1] * n.y + m_inv[2][1] * n.z,
+            m_inv[0][2] * n.x + m_inv[1][2] * n.y + m_inv[2][2] * n.z,
+        )
+    }
+
+    //Determinant of the linear part, i.e. how much the transform scales volumes
+    pub fn determinant(&self) -> fp {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
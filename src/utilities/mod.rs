@@ -1,2 +1,3 @@
 pub mod imageutils;
 pub mod mathutils;
+pub mod sampling;
@@ -0,0 +1,174 @@
+use crate::common::*;
+use std::f64::consts::{FRAC_1_PI, FRAC_PI_2, FRAC_PI_4, PI};
+
+//Warping functions from uniformly distributed samples in [0,1)^2 to other domains.
+//Directions are returned in the local shading space where the normal is +z.
+
+//Shirley-Chiu concentric mapping from the unit square to the unit disk
+pub fn concentric_sample_disk(u: Point2) -> Point2 {
+    let offset: Point2 = Point2::new(2.0 * u.x - 1.0, 2.0 * u.y - 1.0);
+    if offset.x == 0.0 && offset.y == 0.0 {
+        return Point2::new(0.0, 0.0);
+    }
+
+    let (r, theta) = if fp::abs(offset.x) > fp::abs(offset.y) {
+        (offset.x, FRAC_PI_4 * (offset.y / offset.x))
+    } else {
+        (offset.y, FRAC_PI_2 - FRAC_PI_4 * (offset.x / offset.y))
+    };
+    Point2::new(r * theta.cos(), r * theta.sin())
+}
+
+//Malley's method: project a uniformly sampled disk point up onto the hemisphere
+pub fn cosine_sample_hemisphere(u: Point2) -> Vec3 {
+    let d: Point2 = concentric_sample_disk(u);
+    let z: fp = fp::max(0.0, 1.0 - d.x * d.x - d.y * d.y).sqrt();
+    Vec3::new(d.x, d.y, z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: fp) -> fp {
+    fp::max(cos_theta, 0.0) * FRAC_1_PI
+}
+
+pub fn uniform_sample_sphere(u: Point2) -> Vec3 {
+    let z: fp = 1.0 - 2.0 * u.x;
+    let r: fp = fp::max(0.0, 1.0 - z * z).sqrt();
+    let phi: fp = 2.0 * PI * u.y;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> fp {
+    0.25 * FRAC_1_PI
+}
+
+//Veach's power heuristic with beta = 2 for combining two sampling strategies
+pub fn power_heuristic(n_f: fp, f_pdf: fp, n_g: fp, g_pdf: fp) -> fp {
+    let f: fp = n_f * f_pdf;
+    let g: fp = n_g * g_pdf;
+    if f == 0.0 && g == 0.0 {
+        return 0.0;
+    }
+    (f * f) / (f * f + g * g)
+}
+
+//Piecewise constant distribution over [0,1) built from a tabulated function
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<fp>,
+    cdf: Vec<fp>,
+    func_integral: fp,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<fp>) -> Distribution1D {
+        let n: usize = func.len();
+        let mut cdf: Vec<fp> = vec![0.0; n + 1];
+        for i in 1..(n + 1) {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as fp;
+        }
+        let func_integral: fp = cdf[n];
+        //An all zero function is sampled uniformly
+        for (i, value) in cdf.iter_mut().enumerate().skip(1) {
+            *value = if func_integral == 0.0 {
+                i as fp / n as fp
+            } else {
+                *value / func_integral
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            func_integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn func_integral(&self) -> fp {
+        self.func_integral
+    }
+
+    //Sampled point in [0,1), its density and the index of the segment it is in
+    pub fn sample_continuous(&self, u: fp) -> (fp, fp, usize) {
+        //Last cdf entry not above u
+        let offset: usize = self
+            .cdf
+            .partition_point(|value| *value <= u)
+            .clamp(1, self.count())
+            - 1;
+        let mut du: fp = u - self.cdf[offset];
+        let segment_probability: fp = self.cdf[offset + 1] - self.cdf[offset];
+        if segment_probability > 0.0 {
+            du /= segment_probability;
+        }
+        let pdf: fp = if self.func_integral > 0.0 {
+            self.func[offset] / self.func_integral
+        } else {
+            1.0
+        };
+        ((offset as fp + du) / self.count() as fp, pdf, offset)
+    }
+
+    //Sampled segment index and its probability
+    pub fn sample_discrete(&self, u: fp) -> (usize, fp) {
+        let (_, _, offset) = self.sample_continuous(u);
+        (offset, self.discrete_pdf(offset))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> fp {
+        if self.func_integral == 0.0 {
+            return 1.0 / self.count() as fp;
+        }
+        self.func[index] / (self.func_integral * self.count() as fp)
+    }
+
+    //Density at a point in [0,1)
+    pub fn pdf(&self, x: fp) -> fp {
+        if self.func_integral == 0.0 {
+            return 1.0;
+        }
+        let offset: usize = usize::min((x * self.count() as fp) as usize, self.count() - 1);
+        self.func[offset] / self.func_integral
+    }
+}
+
+//Piecewise constant distribution over [0,1)^2, the first coordinate sampled conditionally on the
+//second. func is given row by row, rows along the second coordinate.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[fp], width: usize, height: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = func
+            .chunks(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal =
+            Distribution1D::new(conditional.iter().map(|row| row.func_integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    //Sampled point and its density
+    pub fn sample_continuous(&self, u: Point2) -> (Point2, fp) {
+        let (y, pdf_y, row) = self.marginal.sample_continuous(u.y);
+        let (x, pdf_x, _) = self.conditional[row].sample_continuous(u.x);
+        (Point2::new(x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, p: Point2) -> fp {
+        let row: usize = usize::min(
+            (p.y * self.marginal.count() as fp) as usize,
+            self.marginal.count() - 1,
+        );
+        self.conditional[row].pdf(p.x) * self.marginal.pdf(p.y)
+    }
+}